@@ -3,6 +3,9 @@
 //! Use cases:
 //! $ wgtk pxml show <FILE> [-p <PATH>]
 //! $ wgtk pxml edit <FILE> <PATH> <VALUE>
+//! $ wgtk pxml unpack [--check] <INPUT> [OUTPUT]
+//! $ wgtk pxml pack [--check] <INPUT> [OUTPUT]
+//! $ wgtk res find <RES> <PATTERN>
 
 use std::process::ExitCode;
 
@@ -33,14 +36,28 @@ fn main() -> ExitCode {
                 .about("Edit a terminal value of a given Packed XML file")
                 .arg(arg!(file: <FILE> "The Packed XML file to edit"))
                 .arg(arg!(path: <PATH> "The path to the terminal value to edit"))
-                .arg(arg!(value: <VALUE> "The new value"))))
+                .arg(arg!(value: <VALUE> "The new value")))
+            .subcommand(Command::new("unpack")
+                .about("Unpack Packed XML files to text XML, directories are converted recursively")
+                .arg(arg!(check: -c --check "Only check that files can be converted back without loss, nothing is written"))
+                .arg(arg!(input: <INPUT> "The Packed XML file or directory to unpack"))
+                .arg(arg!(output: [OUTPUT] "The output file or directory, required if not checking")))
+            .subcommand(Command::new("pack")
+                .about("Pack text XML files to Packed XML, directories are converted recursively")
+                .arg(arg!(check: -c --check "Only check that files can be converted back without loss, nothing is written"))
+                .arg(arg!(input: <INPUT> "The text XML file or directory to pack"))
+                .arg(arg!(output: [OUTPUT] "The output file or directory, required if not checking"))))
         .subcommand(Command::new("res")
             .about("Resources flatten filesystem utilities")
             .arg_required_else_help(true)
             .subcommand_required(true)
             .subcommand(Command::new("ls")
                 .about("List files in a given directory")
-                .arg(arg!(res: <RES> "Path to the game's res/ directory"))))
+                .arg(arg!(res: <RES> "Path to the game's res/ directory")))
+            .subcommand(Command::new("find")
+                .about("Find files and directories matching a glob pattern")
+                .arg(arg!(res: <RES> "Path to the game's res/ directory"))
+                .arg(arg!(pattern: <PATTERN> "Glob pattern, such as \"**/*.def\""))))
         .get_matches();
 
     let res = match matches.subcommand() {
@@ -63,6 +80,8 @@ fn cmd_pxml(matches: &ArgMatches) -> CmdResult<()> {
     match matches.subcommand() {
         Some(("show", matches)) => pxml::cmd_pxml_show(matches),
         Some(("edit", matches)) => pxml::cmd_pxml_edit(matches),
+        Some(("unpack", matches)) => pxml::cmd_pxml_unpack(matches),
+        Some(("pack", matches)) => pxml::cmd_pxml_pack(matches),
         _ => unreachable!()
     }
 }
@@ -70,6 +89,7 @@ fn cmd_pxml(matches: &ArgMatches) -> CmdResult<()> {
 fn cmd_res(matches: &ArgMatches) -> CmdResult<()> {
     match matches.subcommand() {
         Some(("ls", matches)) => res::cmd_res_ls(matches),
+        Some(("find", matches)) => res::cmd_res_find(matches),
         _ => unreachable!()
     }
 }
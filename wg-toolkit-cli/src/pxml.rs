@@ -1,8 +1,10 @@
 use std::time::SystemTime;
-use std::path::Path;
-use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::io::Cursor;
+use std::fs::{self, File};
 
 use clap::ArgMatches;
+use xmltree::{EmitterConfig, XMLNode};
 
 use wgtk::pxml::{self, Element, Value};
 
@@ -93,6 +95,269 @@ pub fn cmd_pxml_edit(matches: &ArgMatches) -> CmdResult<()> {
 }
 
 
+pub fn cmd_pxml_unpack(matches: &ArgMatches) -> CmdResult<()> {
+    cmd_pxml_convert(matches, Conversion::Unpack)
+}
+
+
+pub fn cmd_pxml_pack(matches: &ArgMatches) -> CmdResult<()> {
+    cmd_pxml_convert(matches, Conversion::Pack)
+}
+
+
+/// Direction of a conversion between Packed XML and text XML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conversion {
+    Unpack,
+    Pack,
+}
+
+/// Statistics of a conversion.
+#[derive(Debug, Default)]
+struct ConvertStats {
+    converted: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+
+fn cmd_pxml_convert(matches: &ArgMatches, conversion: Conversion) -> CmdResult<()> {
+
+    let input = PathBuf::from(matches.get_one::<String>("input").unwrap());
+    let output = matches.get_one::<String>("output").map(PathBuf::from);
+    let check = matches.get_flag("check");
+
+    if !check && output.is_none() {
+        return Err("An output path is required if not checking.".to_string());
+    }
+
+    let mut stats = ConvertStats::default();
+
+    if input.is_dir() {
+        convert_dir(&input, output.as_deref(), conversion, check, &mut stats)?;
+    } else {
+        match convert_file(&input, output.as_deref(), conversion, check, false) {
+            Ok(_) => stats.converted += 1,
+            Err(message) => {
+                eprintln!("{message}");
+                stats.failed += 1;
+            }
+        }
+    }
+
+    let verb = if check { "checked" } else { "converted" };
+    println!("{} {verb}, {} skipped, {} failed", stats.converted, stats.skipped, stats.failed);
+
+    if stats.failed > 0 {
+        Err(format!("Failed to convert {} files.", stats.failed))
+    } else {
+        Ok(())
+    }
+
+}
+
+
+/// Recursively convert all files of a directory, files that are not in the 
+/// format to convert from are skipped.
+fn convert_dir(
+    input: &Path, 
+    output: Option<&Path>, 
+    conversion: Conversion, 
+    check: bool, 
+    stats: &mut ConvertStats
+) -> CmdResult<()> {
+
+    let read_dir = fs::read_dir(input)
+        .map_err(|e| format!("Failed to read directory at {input:?}, because of: {e}"))?;
+
+    if let Some(output) = output {
+        fs::create_dir_all(output)
+            .map_err(|e| format!("Failed to create directory at {output:?}, because of: {e}"))?;
+    }
+
+    for entry in read_dir {
+
+        let entry = entry.map_err(|e| format!("Failed to read directory at {input:?}, because of: {e}"))?;
+        let entry_path = entry.path();
+        let entry_output = output.map(|output| output.join(entry.file_name()));
+
+        if entry_path.is_dir() {
+            convert_dir(&entry_path, entry_output.as_deref(), conversion, check, stats)?;
+        } else {
+            match convert_file(&entry_path, entry_output.as_deref(), conversion, check, true) {
+                Ok(true) => stats.converted += 1,
+                Ok(false) => stats.skipped += 1,
+                Err(message) => {
+                    eprintln!("{message}");
+                    stats.failed += 1;
+                }
+            }
+        }
+
+    }
+
+    Ok(())
+
+}
+
+
+/// Convert a single file, if skippable is true and the file is not in the
+/// format to convert from, false is returned. When checking, the converted
+/// file is converted back and compared to the original tree, nothing is 
+/// written.
+fn convert_file(
+    input: &Path, 
+    output: Option<&Path>, 
+    conversion: Conversion, 
+    check: bool, 
+    skippable: bool
+) -> CmdResult<bool> {
+
+    let data = fs::read(input)
+        .map_err(|e| format!("Failed to read file at {input:?}, because of: {e}"))?;
+
+    let is_packed = data.starts_with(pxml::MAGIC);
+    let is_text = data.iter().find(|c| !c.is_ascii_whitespace()) == Some(&b'<');
+
+    let (element, converted) = match conversion {
+        Conversion::Unpack => {
+
+            if skippable && !is_packed {
+                return Ok(false);
+            }
+
+            let element = pxml::from_bytes(&data)
+                .map_err(|e| format!("Failed to read Packed XML file at {input:?}, because of: {e}"))?;
+
+            let mut converted = Vec::new();
+            element_to_xml("root", &element)
+                .write_with_config(&mut converted, EmitterConfig::new()
+                    .perform_indent(true)
+                    .write_document_declaration(false))
+                .map_err(|e| format!("Failed to write XML for {input:?}, because of: {e}"))?;
+            converted.push(b'\n');
+
+            (element, converted)
+
+        }
+        Conversion::Pack => {
+
+            if skippable && (is_packed || !is_text) {
+                return Ok(false);
+            }
+
+            let element = Box::new(xml_to_element(&parse_xml(&data, input)?));
+
+            let mut converted = Cursor::new(Vec::new());
+            pxml::to_writer(&mut converted, &element)
+                .map_err(|e| format!("Failed to write Packed XML for {input:?}, because of: {e}"))?;
+
+            (element, converted.into_inner())
+
+        }
+    };
+
+    if check {
+
+        let element_back = match conversion {
+            Conversion::Unpack => Box::new(xml_to_element(&parse_xml(&converted, input)?)),
+            Conversion::Pack => pxml::from_bytes(&converted)
+                .map_err(|e| format!("Failed to read back Packed XML for {input:?}, because of: {e}"))?,
+        };
+
+        if element_back != element {
+            return Err(format!("File at {input:?} can't be converted back without loss."));
+        }
+
+        return Ok(true);
+
+    }
+
+    // Output is checked by the caller when not checking.
+    let output = output.unwrap();
+
+    fs::write(output, &converted)
+        .map_err(|e| format!("Failed to write file at {output:?}, because of: {e}"))?;
+
+    // Preserve the modification time of the original file.
+    if let Ok(modified) = fs::metadata(input).and_then(|meta| meta.modified()) {
+        File::options().write(true).open(output)
+            .and_then(|file| file.set_modified(modified))
+            .map_err(|e| format!("Failed to set modification time of {output:?}, because of: {e}"))?;
+    }
+
+    Ok(true)
+
+}
+
+
+/// Parse a text XML document.
+fn parse_xml(data: &[u8], path: &Path) -> CmdResult<xmltree::Element> {
+    xmltree::Element::parse(data)
+        .map_err(|e| format!("Failed to read XML file at {path:?}, because of: {e}"))
+}
+
+
+/// Convert a Packed XML element to a text XML element with the given name.
+fn element_to_xml(name: &str, element: &Element) -> xmltree::Element {
+
+    let mut xml_element = xmltree::Element::new(name);
+
+    if let Some(text) = element.value.to_text() {
+        if !text.is_empty() {
+            xml_element.children.push(XMLNode::Text(text));
+        }
+    }
+
+    for (child_key, child_value) in element.iter_children_all() {
+        let xml_child = match child_value {
+            Value::Element(child_element) => element_to_xml(child_key, child_element),
+            value => {
+                let mut xml_child = xmltree::Element::new(child_key);
+                // Unwrap because this is not an element.
+                let text = value.to_text().unwrap();
+                if !text.is_empty() {
+                    xml_child.children.push(XMLNode::Text(text));
+                }
+                xml_child
+            }
+        };
+        xml_element.children.push(XMLNode::Element(xml_child));
+    }
+
+    xml_element
+
+}
+
+
+/// Convert a text XML element to a Packed XML element, the element's name
+/// is ignored.
+fn xml_to_element(xml_element: &xmltree::Element) -> Element {
+
+    let mut element = Element::new();
+    element.value = Value::from_text(xml_text(xml_element).trim());
+
+    for xml_child in xml_element.children.iter().filter_map(XMLNode::as_element) {
+        // Elements without children are terminal values.
+        let value = if xml_child.children.iter().any(|node| node.as_element().is_some()) {
+            Value::Element(Box::new(xml_to_element(xml_child)))
+        } else {
+            Value::from_text(xml_text(xml_child).trim())
+        };
+        element.add_children(xml_child.name.clone(), value);
+    }
+
+    element
+
+}
+
+
+/// Get the concatenated text of a text XML element.
+fn xml_text(xml_element: &xmltree::Element) -> String {
+    xml_element.get_text().map(|text| text.into_owned()).unwrap_or_default()
+}
+
+
 fn cmd_read_pxml_file<P: AsRef<Path>>(path: P) -> CmdResult<Box<Element>> {
 
     let path = path.as_ref();
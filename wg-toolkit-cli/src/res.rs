@@ -20,3 +20,25 @@ pub fn cmd_res_ls(matches: &ArgMatches) -> CmdResult<()> {
     Ok(())
 
 }
+
+
+pub fn cmd_res_find(matches: &ArgMatches) -> CmdResult<()> {
+
+    let res_dir_path = matches.get_one::<String>("res").unwrap();
+    let pattern = matches.get_one::<String>("pattern").unwrap();
+
+    let mut fs = ResFilesystem::new(res_dir_path)
+        .map_err(|e| format!("Failed to open resources: {e}"))?;
+
+    for entry in fs.find(pattern) {
+        let entry = entry.map_err(|e| format!("Failed to find resources: {e}"))?;
+        if entry.is_dir() {
+            println!("{}/", entry.path());
+        } else {
+            println!("{}", entry.path());
+        }
+    }
+
+    Ok(())
+
+}
@@ -0,0 +1,111 @@
+/* C ABI for wg-toolkit, see wg-toolkit-ffi/src/lib.rs for details. */
+
+#ifndef WGTK_H
+#define WGTK_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define WGTK_OK 0
+#define WGTK_ERR_NULL -1
+#define WGTK_ERR_TOO_LONG -2
+#define WGTK_ERR_INVALID_PACKET -3
+#define WGTK_ERR_INVALID_ELEMENT -4
+#define WGTK_ERR_OUT_OF_BOUNDS -5
+#define WGTK_ERR_PANIC -6
+
+#define WGTK_LEN_UNKNOWN 0
+#define WGTK_LEN_VAR8 1
+#define WGTK_LEN_VAR16 2
+#define WGTK_LEN_VAR24 3
+#define WGTK_LEN_VAR32 4
+#define WGTK_LEN_FIXED 5
+
+#define WGTK_PXML_ELEMENT 0
+#define WGTK_PXML_STRING 1
+#define WGTK_PXML_INTEGER 2
+#define WGTK_PXML_BOOLEAN 3
+#define WGTK_PXML_FLOAT 4
+#define WGTK_PXML_VEC3 5
+#define WGTK_PXML_AFFINE3 6
+
+typedef struct wgtk_bundle wgtk_bundle;
+typedef struct wgtk_element_list wgtk_element_list;
+typedef struct wgtk_pxml_element wgtk_pxml_element;
+typedef struct wgtk_pxml_value wgtk_pxml_value;
+
+/* A borrowed, non null-terminated, UTF-8 string. */
+typedef struct wgtk_str {
+    const uint8_t *ptr;
+    size_t len;
+} wgtk_str;
+
+/* Length of an element, fixed_len is only used by WGTK_LEN_FIXED. */
+typedef struct wgtk_element_length {
+    uint8_t kind;
+    uint32_t fixed_len;
+} wgtk_element_length;
+
+typedef struct wgtk_packet_info {
+    bool has_prefix;
+    uint32_t prefix;
+    bool has_seq;
+    uint32_t seq_first;
+    uint32_t seq_last;
+    uint32_t seq;
+    bool has_checksum;
+    uint32_t request_first_offset;
+    size_t body_offset;
+    size_t body_len;
+} wgtk_packet_info;
+
+typedef struct wgtk_element {
+    uint8_t id;
+    bool is_reply;
+    uint32_t reply_to;
+    bool has_request_id;
+    uint32_t request_id;
+    const uint8_t *data;
+    size_t data_len;
+} wgtk_element;
+
+/* Packet */
+int wgtk_packet_decode(const uint8_t *data, size_t len, bool has_prefix, wgtk_packet_info *info);
+
+/* Bundle */
+wgtk_bundle *wgtk_bundle_new(bool has_prefix);
+void wgtk_bundle_free(wgtk_bundle *bundle);
+int wgtk_bundle_add_packet(wgtk_bundle *bundle, const uint8_t *data, size_t len);
+int wgtk_bundle_decode(const wgtk_bundle *bundle, const wgtk_element_length lengths[256], wgtk_element_list **list);
+
+void wgtk_element_list_free(wgtk_element_list *list);
+size_t wgtk_element_list_len(const wgtk_element_list *list);
+int wgtk_element_list_unknown_id(const wgtk_element_list *list);
+int wgtk_element_list_get(const wgtk_element_list *list, size_t index, wgtk_element *element);
+
+/* Packed XML */
+wgtk_pxml_element *wgtk_pxml_load(const uint8_t *data, size_t len);
+void wgtk_pxml_free(wgtk_pxml_element *root);
+const wgtk_pxml_value *wgtk_pxml_element_value(const wgtk_pxml_element *element);
+size_t wgtk_pxml_element_children_len(const wgtk_pxml_element *element);
+const wgtk_pxml_value *wgtk_pxml_element_child(const wgtk_pxml_element *element, size_t index, wgtk_str *name);
+
+uint32_t wgtk_pxml_value_kind(const wgtk_pxml_value *value);
+const wgtk_pxml_element *wgtk_pxml_value_as_element(const wgtk_pxml_value *value);
+bool wgtk_pxml_value_as_string(const wgtk_pxml_value *value, wgtk_str *out);
+bool wgtk_pxml_value_as_integer(const wgtk_pxml_value *value, int64_t *out);
+bool wgtk_pxml_value_as_boolean(const wgtk_pxml_value *value, bool *out);
+bool wgtk_pxml_value_as_float(const wgtk_pxml_value *value, float *out);
+bool wgtk_pxml_value_as_vec3(const wgtk_pxml_value *value, float out[3]);
+bool wgtk_pxml_value_as_affine3(const wgtk_pxml_value *value, float out[12]);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
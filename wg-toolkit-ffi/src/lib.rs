@@ -0,0 +1,634 @@
+//! C ABI for wg-toolkit, the matching header is `include/wgtk.h`.
+//!
+//! Every object is exposed through an opaque handle allocated by this
+//! library, handles returned by `*_new` or `*_load` functions must be given
+//! back to the matching `*_free` function. Pointers returned from a handle
+//! (values, strings, element data) are borrowed and remain valid until the
+//! owning handle is freed.
+//!
+//! Functions returning `int` return `WGTK_OK` (zero) on success and a
+//! negative `WGTK_ERR_*` code on error. Unless stated otherwise, all pointer
+//! arguments must be non-null and valid for the given length.
+//!
+//! Panics never unwind into the caller, a function that panics returns
+//! `WGTK_ERR_PANIC`, or null, false or zero for functions not returning an
+//! error code.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::panic::{self, AssertUnwindSafe};
+use std::ffi::c_int;
+use std::ptr;
+
+use wgtk::pxml::{self, Element, Value};
+use wgtk::net::packet::{Packet, PACKET_MAX_LEN, PACKET_PREFIX_LEN, PACKET_FLAGS_LEN};
+use wgtk::net::bundle::{Bundle, BundleElement};
+use wgtk::net::element::{ElementLength, RawElementCodec, RawElementCodecLenVar32};
+
+
+pub const WGTK_OK: c_int = 0;
+pub const WGTK_ERR_NULL: c_int = -1;
+pub const WGTK_ERR_TOO_LONG: c_int = -2;
+pub const WGTK_ERR_INVALID_PACKET: c_int = -3;
+pub const WGTK_ERR_INVALID_ELEMENT: c_int = -4;
+pub const WGTK_ERR_OUT_OF_BOUNDS: c_int = -5;
+pub const WGTK_ERR_PANIC: c_int = -6;
+
+pub const WGTK_LEN_UNKNOWN: u8 = 0;
+pub const WGTK_LEN_VAR8: u8 = 1;
+pub const WGTK_LEN_VAR16: u8 = 2;
+pub const WGTK_LEN_VAR24: u8 = 3;
+pub const WGTK_LEN_VAR32: u8 = 4;
+pub const WGTK_LEN_FIXED: u8 = 5;
+
+pub const WGTK_PXML_ELEMENT: u32 = 0;
+pub const WGTK_PXML_STRING: u32 = 1;
+pub const WGTK_PXML_INTEGER: u32 = 2;
+pub const WGTK_PXML_BOOLEAN: u32 = 3;
+pub const WGTK_PXML_FLOAT: u32 = 4;
+pub const WGTK_PXML_VEC3: u32 = 5;
+pub const WGTK_PXML_AFFINE3: u32 = 6;
+
+
+/// A borrowed, non null-terminated, UTF-8 string.
+#[repr(C)]
+pub struct WgtkStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+/// Length of an element, `fixed_len` is only used by `WGTK_LEN_FIXED`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct WgtkElementLength {
+    pub kind: u8,
+    pub fixed_len: u32,
+}
+
+/// Information about a decoded packet.
+#[repr(C)]
+pub struct WgtkPacketInfo {
+    pub has_prefix: bool,
+    pub prefix: u32,
+    pub has_seq: bool,
+    pub seq_first: u32,
+    pub seq_last: u32,
+    pub seq: u32,
+    pub has_checksum: bool,
+    pub request_first_offset: u32,
+    /// Offset of the body in the given datagram.
+    pub body_offset: usize,
+    pub body_len: usize,
+}
+
+/// A decoded element, borrowed from its element list.
+#[repr(C)]
+pub struct WgtkElement {
+    /// Identifier of the element, `0xFF` for replies.
+    pub id: u8,
+    pub is_reply: bool,
+    /// For replies, the request ID this reply is for.
+    pub reply_to: u32,
+    pub has_request_id: bool,
+    pub request_id: u32,
+    pub data: *const u8,
+    pub data_len: usize,
+}
+
+/// Handle to a bundle being assembled from datagrams.
+pub struct WgtkBundle {
+    /// Checked datagrams, the packets are loaded again on each decoding.
+    datagrams: Vec<Vec<u8>>,
+    has_prefix: bool,
+}
+
+/// Handle to the list of elements decoded from a bundle.
+pub struct WgtkElementList {
+    elements: Vec<OwnedElement>,
+    /// Set to the ID of the element that stopped the decoding, if any.
+    unknown_id: Option<u8>,
+}
+
+struct OwnedElement {
+    id: u8,
+    reply_to: Option<u32>,
+    request_id: Option<u32>,
+    data: Vec<u8>,
+}
+
+
+// Packet
+
+/// Decode the header and footers of a single datagram.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_packet_decode(data: *const u8, len: usize, has_prefix: bool, info: *mut WgtkPacketInfo) -> c_int {
+    guard(WGTK_ERR_PANIC, || {
+
+        if data.is_null() || info.is_null() {
+            return WGTK_ERR_NULL;
+        }
+
+        let packet = match load_packet(std::slice::from_raw_parts(data, len), has_prefix) {
+            Ok(packet) => packet,
+            Err(code) => return code,
+        };
+
+        let (seq_first, seq_last, seq) = packet.get_seq();
+        let prefix_len = if has_prefix { PACKET_PREFIX_LEN } else { 0 };
+
+        info.write(WgtkPacketInfo {
+            has_prefix,
+            prefix: packet.get_prefix().unwrap_or(0),
+            has_seq: packet.has_seq(),
+            seq_first,
+            seq_last,
+            seq,
+            has_checksum: packet.has_checksum(),
+            request_first_offset: packet.get_request_first_offset() as u32,
+            body_offset: prefix_len + PACKET_FLAGS_LEN,
+            body_len: packet.get_body_data().len(),
+        });
+
+        WGTK_OK
+
+    })
+}
+
+
+// Bundle
+
+#[no_mangle]
+pub extern "C" fn wgtk_bundle_new(has_prefix: bool) -> *mut WgtkBundle {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(WgtkBundle {
+            datagrams: Vec::new(),
+            has_prefix,
+        }))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_bundle_free(bundle: *mut WgtkBundle) {
+    guard((), || {
+        if !bundle.is_null() {
+            drop(Box::from_raw(bundle));
+        }
+    })
+}
+
+/// Append a datagram to the bundle, datagrams must be given in order.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_bundle_add_packet(bundle: *mut WgtkBundle, data: *const u8, len: usize) -> c_int {
+    guard(WGTK_ERR_PANIC, || {
+
+        if bundle.is_null() || data.is_null() {
+            return WGTK_ERR_NULL;
+        }
+
+        let bundle = &mut *bundle;
+        let data = std::slice::from_raw_parts(data, len);
+        match load_packet(data, bundle.has_prefix) {
+            Ok(_) => {
+                bundle.datagrams.push(data.to_vec());
+                WGTK_OK
+            }
+            Err(code) => code,
+        }
+
+    })
+}
+
+/// Decode all elements of the bundle. Because the length of an element
+/// depends on its ID, the `lengths` table of 256 entries must give the
+/// length of each element ID. Decoding stops on the first element with an
+/// unknown length, see `wgtk_element_list_unknown_id`.
+///
+/// On success, the returned list must be freed with `wgtk_element_list_free`.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_bundle_decode(bundle: *const WgtkBundle, lengths: *const WgtkElementLength, list: *mut *mut WgtkElementList) -> c_int {
+    guard(WGTK_ERR_PANIC, || {
+
+        if bundle.is_null() || lengths.is_null() || list.is_null() {
+            return WGTK_ERR_NULL;
+        }
+
+        let bundle = &*bundle;
+        let lengths = std::slice::from_raw_parts(lengths, 256);
+
+        let packets = bundle.datagrams.iter()
+            .map(|data| load_packet(data, bundle.has_prefix))
+            .collect::<Result<Vec<_>, _>>();
+
+        let packets = match packets {
+            Ok(packets) => packets,
+            Err(code) => return code,
+        };
+
+        let tmp_bundle = Bundle::from_packets(packets, bundle.has_prefix);
+        let mut reader = tmp_bundle.get_element_reader();
+        let mut elements = Vec::new();
+        let mut unknown_id = None;
+
+        while let Some(elt) = reader.next_element() {
+            let res = match elt {
+                BundleElement::Simple(id, elt) => {
+                    let Some(length) = element_length(lengths[id as usize]) else {
+                        unknown_id = Some(id);
+                        break;
+                    };
+                    elt.read_raw(length)
+                        .map(|elt| OwnedElement { id, reply_to: None, request_id: elt.request_id, data: elt.element })
+                }
+                BundleElement::Reply(request_id, elt) => {
+                    elt.read(&RawElementCodec::<RawElementCodecLenVar32>::new())
+                        .map(|elt| OwnedElement { id: 0xFF, reply_to: Some(request_id), request_id: elt.request_id, data: elt.element })
+                }
+            };
+            match res {
+                Ok(elt) => elements.push(elt),
+                Err(_) => return WGTK_ERR_INVALID_ELEMENT,
+            }
+        }
+
+        list.write(Box::into_raw(Box::new(WgtkElementList { elements, unknown_id })));
+        WGTK_OK
+
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_element_list_free(list: *mut WgtkElementList) {
+    guard((), || {
+        if !list.is_null() {
+            drop(Box::from_raw(list));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_element_list_len(list: *const WgtkElementList) -> usize {
+    guard(0, || {
+        if list.is_null() { 0 } else { (&*list).elements.len() }
+    })
+}
+
+/// Return the ID of the element with an unknown length that stopped the
+/// decoding, or -1 if all elements have been decoded.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_element_list_unknown_id(list: *const WgtkElementList) -> c_int {
+    guard(WGTK_ERR_PANIC, || {
+        match list.as_ref().and_then(|list| list.unknown_id) {
+            Some(id) => id as c_int,
+            None => -1,
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_element_list_get(list: *const WgtkElementList, index: usize, element: *mut WgtkElement) -> c_int {
+    guard(WGTK_ERR_PANIC, || {
+
+        if list.is_null() || element.is_null() {
+            return WGTK_ERR_NULL;
+        }
+
+        let Some(elt) = (&*list).elements.get(index) else {
+            return WGTK_ERR_OUT_OF_BOUNDS;
+        };
+
+        element.write(WgtkElement {
+            id: elt.id,
+            is_reply: elt.reply_to.is_some(),
+            reply_to: elt.reply_to.unwrap_or(0),
+            has_request_id: elt.request_id.is_some(),
+            request_id: elt.request_id.unwrap_or(0),
+            data: elt.data.as_ptr(),
+            data_len: elt.data.len(),
+        });
+
+        WGTK_OK
+
+    })
+}
+
+
+// Packed XML
+
+/// Load a packed XML file from its bytes, return null if invalid. The
+/// returned root element must be freed with `wgtk_pxml_free`.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_load(data: *const u8, len: usize) -> *mut Element {
+    guard(ptr::null_mut(), || {
+        if data.is_null() {
+            return ptr::null_mut();
+        }
+        match pxml::from_bytes(std::slice::from_raw_parts(data, len)) {
+            Ok(elt) => Box::into_raw(elt),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_free(root: *mut Element) {
+    guard((), || {
+        if !root.is_null() {
+            drop(Box::from_raw(root));
+        }
+    })
+}
+
+/// Return the proper value of an element.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_element_value(element: *const Element) -> *const Value {
+    guard(ptr::null(), || {
+        &(*element).value
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_element_children_len(element: *const Element) -> usize {
+    guard(0, || {
+        (&*element).iter_children_all().count()
+    })
+}
+
+/// Return the child value at the given index and write its name, return
+/// null if out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_element_child(element: *const Element, index: usize, name: *mut WgtkStr) -> *const Value {
+    guard(ptr::null(), || {
+        match (&*element).iter_children_all().nth(index) {
+            Some((child_name, value)) => {
+                if !name.is_null() {
+                    name.write(WgtkStr { ptr: child_name.as_ptr(), len: child_name.len() });
+                }
+                value
+            }
+            None => ptr::null(),
+        }
+    })
+}
+
+/// Return the `WGTK_PXML_*` kind of the value, or `UINT32_MAX` on panic.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_kind(value: *const Value) -> u32 {
+    guard(u32::MAX, || {
+        match &*value {
+            Value::Element(_) => WGTK_PXML_ELEMENT,
+            Value::String(_) => WGTK_PXML_STRING,
+            Value::Integer(_) => WGTK_PXML_INTEGER,
+            Value::Boolean(_) => WGTK_PXML_BOOLEAN,
+            Value::Float(_) => WGTK_PXML_FLOAT,
+            Value::Vec3(_) => WGTK_PXML_VEC3,
+            Value::Affine3(_) => WGTK_PXML_AFFINE3,
+        }
+    })
+}
+
+/// Return the value as an element, or null if not an element.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_element(value: *const Value) -> *const Element {
+    guard(ptr::null(), || {
+        (&*value).as_element().map_or(ptr::null(), |elt| elt as *const Element)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_string(value: *const Value, out: *mut WgtkStr) -> bool {
+    guard(false, || {
+        match (&*value).as_string() {
+            Some(s) => { out.write(WgtkStr { ptr: s.as_ptr(), len: s.len() }); true }
+            None => false,
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_integer(value: *const Value, out: *mut i64) -> bool {
+    guard(false, || {
+        (&*value).as_integer().map(|n| out.write(n)).is_some()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_boolean(value: *const Value, out: *mut bool) -> bool {
+    guard(false, || {
+        (&*value).as_boolean().map(|b| out.write(b)).is_some()
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_float(value: *const Value, out: *mut f32) -> bool {
+    guard(false, || {
+        (&*value).as_float().map(|n| out.write(n)).is_some()
+    })
+}
+
+/// Write the 3 components of a vec3 value.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_vec3(value: *const Value, out: *mut f32) -> bool {
+    guard(false, || {
+        (&*value).as_vec3().map(|v| ptr::copy_nonoverlapping(v.to_array().as_ptr(), out, 3)).is_some()
+    })
+}
+
+/// Write the 12 components of an affine3 value, column-major.
+#[no_mangle]
+pub unsafe extern "C" fn wgtk_pxml_value_as_affine3(value: *const Value, out: *mut f32) -> bool {
+    guard(false, || {
+        (&*value).as_affine3().map(|a| ptr::copy_nonoverlapping(a.to_cols_array().as_ptr(), out, 12)).is_some()
+    })
+}
+
+
+/// Internal function to call a function, returning the given value if it
+/// panics instead of unwinding into the caller.
+fn guard<T>(panic_value: T, func: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(func)).unwrap_or(panic_value)
+}
+
+/// Internal function to get the element length from its C description.
+fn element_length(length: WgtkElementLength) -> Option<ElementLength> {
+    Some(match length.kind {
+        WGTK_LEN_VAR8 => ElementLength::Variable8,
+        WGTK_LEN_VAR16 => ElementLength::Variable16,
+        WGTK_LEN_VAR24 => ElementLength::Variable24,
+        WGTK_LEN_VAR32 => ElementLength::Variable32,
+        WGTK_LEN_FIXED => ElementLength::Fixed(length.fixed_len),
+        _ => return None,
+    })
+}
+
+/// Internal function to load a packet from a received datagram.
+fn load_packet(data: &[u8], has_prefix: bool) -> Result<Box<Packet>, c_int> {
+    if data.len() > PACKET_MAX_LEN {
+        return Err(WGTK_ERR_TOO_LONG);
+    }
+    let mut packet = Packet::new_boxed(has_prefix);
+    packet.get_raw_data_mut()[..data.len()].copy_from_slice(data);
+    packet.sync_state(data.len()).map_err(|_| WGTK_ERR_INVALID_PACKET)?;
+    Ok(packet)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+    use std::slice;
+
+    use wgtk::net::element::{FixedElementCodec, Var16ElementCodec};
+
+    use super::*;
+
+    const UNKNOWN: WgtkElementLength = WgtkElementLength { kind: WGTK_LEN_UNKNOWN, fixed_len: 0 };
+
+    /// Create the datagrams of a bundle with a fixed ping element, a request
+    /// and a reply.
+    fn datagrams() -> Vec<Vec<u8>> {
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x00, &FixedElementCodec::<1>::new(), vec![7]);
+        bundle.add_request(0x01, &Var16ElementCodec::new(), vec![1, 2, 3], 5);
+        bundle.add_reply(&Var16ElementCodec::new(), vec![9, 9], 3);
+        bundle.finalize(&mut 0);
+        bundle.get_packets().iter()
+            .map(|packet| packet.get_raw_data()[..packet.raw_len()].to_vec())
+            .collect()
+    }
+
+    /// Decode the bundle of the given datagrams through the C functions.
+    unsafe fn decode(datagrams: &[Vec<u8>], lengths: &[WgtkElementLength; 256]) -> *mut WgtkElementList {
+        let bundle = wgtk_bundle_new(false);
+        for data in datagrams {
+            assert_eq!(wgtk_bundle_add_packet(bundle, data.as_ptr(), data.len()), WGTK_OK);
+        }
+        let mut list = ptr::null_mut();
+        assert_eq!(wgtk_bundle_decode(bundle, lengths.as_ptr(), &mut list), WGTK_OK);
+        wgtk_bundle_free(bundle);
+        list
+    }
+
+    unsafe fn get_element(list: *const WgtkElementList, index: usize) -> WgtkElement {
+        let mut element = std::mem::MaybeUninit::uninit();
+        assert_eq!(wgtk_element_list_get(list, index, element.as_mut_ptr()), WGTK_OK);
+        element.assume_init()
+    }
+
+    #[test]
+    fn packet_decode() {
+        unsafe {
+
+            let data = &datagrams()[0];
+            let mut info = std::mem::MaybeUninit::uninit();
+            assert_eq!(wgtk_packet_decode(data.as_ptr(), data.len(), false, info.as_mut_ptr()), WGTK_OK);
+            let info = info.assume_init();
+            assert!(!info.has_prefix);
+            assert!(!info.has_seq);
+            assert_eq!(info.body_offset, PACKET_FLAGS_LEN);
+            assert_ne!(info.request_first_offset, 0);
+
+            let mut info = std::mem::MaybeUninit::uninit();
+            assert_eq!(wgtk_packet_decode(ptr::null(), 0, false, info.as_mut_ptr()), WGTK_ERR_NULL);
+            assert_eq!(wgtk_packet_decode(data.as_ptr(), 1, false, info.as_mut_ptr()), WGTK_ERR_INVALID_PACKET);
+            let long = vec![0; PACKET_MAX_LEN + 1];
+            assert_eq!(wgtk_packet_decode(long.as_ptr(), long.len(), false, info.as_mut_ptr()), WGTK_ERR_TOO_LONG);
+
+        }
+    }
+
+    #[test]
+    fn bundle_decode() {
+        unsafe {
+
+            let mut lengths = [UNKNOWN; 256];
+            lengths[0x00] = WgtkElementLength { kind: WGTK_LEN_FIXED, fixed_len: 1 };
+            lengths[0x01] = WgtkElementLength { kind: WGTK_LEN_VAR16, fixed_len: 0 };
+
+            let list = decode(&datagrams(), &lengths);
+            assert_eq!(wgtk_element_list_len(list), 3);
+            assert_eq!(wgtk_element_list_unknown_id(list), -1);
+
+            let ping = get_element(list, 0);
+            assert_eq!(ping.id, 0x00);
+            assert!(!ping.is_reply && !ping.has_request_id);
+            assert_eq!(slice::from_raw_parts(ping.data, ping.data_len), [7]);
+
+            let request = get_element(list, 1);
+            assert_eq!(request.id, 0x01);
+            assert!(request.has_request_id);
+            assert_eq!(request.request_id, 5);
+            assert_eq!(slice::from_raw_parts(request.data, request.data_len), [1, 2, 3]);
+
+            let reply = get_element(list, 2);
+            assert_eq!(reply.id, 0xFF);
+            assert!(reply.is_reply);
+            assert_eq!(reply.reply_to, 3);
+            assert_eq!(slice::from_raw_parts(reply.data, reply.data_len), [9, 9]);
+
+            let mut element = std::mem::MaybeUninit::uninit();
+            assert_eq!(wgtk_element_list_get(list, 3, element.as_mut_ptr()), WGTK_ERR_OUT_OF_BOUNDS);
+            assert_eq!(wgtk_element_list_get(ptr::null(), 0, element.as_mut_ptr()), WGTK_ERR_NULL);
+            wgtk_element_list_free(list);
+
+            // The request has an unknown length, decoding stops on it.
+            lengths[0x01] = UNKNOWN;
+            let list = decode(&datagrams(), &lengths);
+            assert_eq!(wgtk_element_list_len(list), 1);
+            assert_eq!(wgtk_element_list_unknown_id(list), 0x01);
+            wgtk_element_list_free(list);
+
+            let mut list = ptr::null_mut();
+            assert_eq!(wgtk_bundle_decode(ptr::null(), lengths.as_ptr(), &mut list), WGTK_ERR_NULL);
+            assert_eq!(wgtk_element_list_len(ptr::null()), 0);
+            assert_eq!(wgtk_element_list_unknown_id(ptr::null()), -1);
+
+        }
+    }
+
+    #[test]
+    fn pxml() {
+        unsafe {
+
+            let mut root = Element::new();
+            root.add_children("name", Value::String("test".to_string()));
+            root.add_children("count", Value::Integer(42));
+            let mut data = Cursor::new(Vec::new());
+            pxml::to_writer(&mut data, &root).unwrap();
+            let data = data.into_inner();
+
+            let root = wgtk_pxml_load(data.as_ptr(), data.len());
+            assert!(!root.is_null());
+            assert_eq!(wgtk_pxml_element_children_len(root), 2);
+
+            let mut name = WgtkStr { ptr: ptr::null(), len: 0 };
+            let value = wgtk_pxml_element_child(root, 0, &mut name);
+            assert_eq!(slice::from_raw_parts(name.ptr, name.len), b"name");
+            assert_eq!(wgtk_pxml_value_kind(value), WGTK_PXML_STRING);
+            let mut string = WgtkStr { ptr: ptr::null(), len: 0 };
+            assert!(wgtk_pxml_value_as_string(value, &mut string));
+            assert_eq!(slice::from_raw_parts(string.ptr, string.len), b"test");
+
+            let value = wgtk_pxml_element_child(root, 1, ptr::null_mut());
+            let mut integer = 0;
+            assert!(wgtk_pxml_value_as_integer(value, &mut integer));
+            assert_eq!(integer, 42);
+            assert!(!wgtk_pxml_value_as_string(value, &mut string));
+
+            assert!(wgtk_pxml_element_child(root, 2, ptr::null_mut()).is_null());
+            wgtk_pxml_free(root);
+
+            assert!(wgtk_pxml_load(b"invalid".as_ptr(), 7).is_null());
+            assert!(wgtk_pxml_load(ptr::null(), 0).is_null());
+
+        }
+    }
+
+    #[test]
+    fn guard_panic() {
+        assert_eq!(guard(WGTK_ERR_PANIC, || panic!("test")), WGTK_ERR_PANIC);
+        assert!(guard(ptr::null::<u8>(), || panic!("test")).is_null());
+        assert_eq!(guard(WGTK_OK, || WGTK_OK), WGTK_OK);
+    }
+
+}
@@ -0,0 +1,280 @@
+//! Python bindings for wg-toolkit.
+//!
+//! The API surface is intentionally thin: raw bytes are given in and plain
+//! Python objects (dicts, lists, tuples) are returned.
+//!
+//! ```python
+//! import wgtk_py as wgtk
+//! root = wgtk.pxml_from_bytes(open("settings.xml", "rb").read())
+//! packet = wgtk.packet_decode(datagram)
+//! elements = wgtk.bundle_elements([datagram], {0x00: 1, 0x01: "var16"})
+//! ```
+
+// Triggered by the code generated by `#[pyfunction]`.
+#![allow(clippy::useless_conversion)]
+
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use pyo3::prelude::*;
+
+use wgtk::pxml::{self, Element, Value};
+use wgtk::net::packet::{Packet, PACKET_MAX_LEN};
+use wgtk::net::bundle::{Bundle, BundleElement};
+use wgtk::net::element::{ElementLength, RawElementCodec, RawElementCodecLenVar32};
+
+
+#[pymodule]
+fn wgtk_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(pxml_from_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(packet_decode, m)?)?;
+    m.add_function(wrap_pyfunction!(bundle_elements, m)?)?;
+    Ok(())
+}
+
+
+/// Decode a packed XML file from its raw bytes. The returned element is a
+/// dict with a `value` key and a `children` list of `(name, value)` tuples,
+/// because children names are not guaranteed to be unique.
+#[pyfunction]
+fn pxml_from_bytes<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyAny>> {
+    let elt = pxml::from_bytes(data)
+        .map_err(|e| PyValueError::new_err(format!("invalid packed xml: {e}")))?;
+    pxml_element_to_py(py, &elt)
+}
+
+/// Decode a single datagram into a packet description dict, containing the
+/// prefix (if any), the footer values and the body bytes.
+#[pyfunction]
+#[pyo3(signature = (data, has_prefix = false))]
+fn packet_decode<'py>(py: Python<'py>, data: &[u8], has_prefix: bool) -> PyResult<Bound<'py, PyDict>> {
+    let packet = load_packet(data, has_prefix).map_err(PyValueError::new_err)?;
+    let (seq_first, seq_last, seq) = packet.get_seq();
+    let dict = PyDict::new_bound(py);
+    dict.set_item("prefix", packet.get_prefix())?;
+    dict.set_item("has_checksum", packet.has_checksum())?;
+    if packet.has_seq() {
+        dict.set_item("seq_first", seq_first)?;
+        dict.set_item("seq_last", seq_last)?;
+        dict.set_item("seq", seq)?;
+    }
+    dict.set_item("request_first_offset", packet.get_request_first_offset())?;
+    dict.set_item("body", PyBytes::new_bound(py, packet.get_body_data()))?;
+    Ok(dict)
+}
+
+/// Iterate all elements of a bundle made of the given datagrams. Because the
+/// length of an element depends on its ID, the `lengths` dict must map each
+/// expected element ID to its fixed size as an int, or to one of `"var8"`,
+/// `"var16"`, `"var24"` or `"var32"`. Replies are always decoded because
+/// their length is known.
+///
+/// Each element is returned as a dict with its raw `data` bytes and either an
+/// `id` or a `reply_to` key. Iteration stops on the first element with an
+/// unknown ID, in which case the last dict only contains this `id`.
+#[pyfunction]
+#[pyo3(signature = (packets, lengths, has_prefix = false))]
+fn bundle_elements<'py>(
+    py: Python<'py>,
+    packets: Vec<Vec<u8>>,
+    lengths: &Bound<'py, PyDict>,
+    has_prefix: bool,
+) -> PyResult<Bound<'py, PyList>> {
+
+    let mut lengths_table = [None; 256];
+    for (id, kind) in lengths.iter() {
+        let id = id.extract::<u8>()?;
+        lengths_table[id as usize] = Some(match kind.extract::<u32>() {
+            Ok(len) => ElementLength::Fixed(len),
+            Err(_) => {
+                let name = kind.extract::<String>()?;
+                parse_length(&name).ok_or_else(|| PyValueError::new_err(format!("invalid length kind: {name}")))?
+            }
+        });
+    }
+
+    let (elements, unknown_id) = decode_elements(&packets, has_prefix, &lengths_table)
+        .map_err(PyValueError::new_err)?;
+
+    let list = PyList::empty_bound(py);
+
+    for elt in elements {
+        let dict = PyDict::new_bound(py);
+        match elt.reply_to {
+            Some(request_id) => dict.set_item("reply_to", request_id)?,
+            None => dict.set_item("id", elt.id)?,
+        }
+        dict.set_item("request_id", elt.request_id)?;
+        dict.set_item("data", PyBytes::new_bound(py, &elt.data))?;
+        list.append(dict)?;
+    }
+
+    if let Some(id) = unknown_id {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("id", id)?;
+        list.append(dict)?;
+    }
+
+    Ok(list)
+
+}
+
+
+/// An element decoded from a bundle.
+#[derive(Debug, PartialEq, Eq)]
+struct DecodedElement {
+    /// Identifier of the element, `0xFF` for replies.
+    id: u8,
+    /// For replies, the request ID this reply is for.
+    reply_to: Option<u32>,
+    request_id: Option<u32>,
+    data: Vec<u8>,
+}
+
+/// Internal function to decode all elements of a bundle made of the given
+/// datagrams, with the length of each element ID. The ID of the element with
+/// an unknown length that stopped the decoding is also returned.
+///
+/// Errors are plain strings, so that this can be tested without Python.
+fn decode_elements(packets: &[Vec<u8>], has_prefix: bool, lengths: &[Option<ElementLength>; 256]) -> Result<(Vec<DecodedElement>, Option<u8>), String> {
+
+    let packets = packets.iter()
+        .map(|data| load_packet(data, has_prefix))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bundle = Bundle::from_packets(packets, has_prefix);
+    let mut reader = bundle.get_element_reader();
+    let mut elements = Vec::new();
+
+    while let Some(elt) = reader.next_element() {
+        match elt {
+            BundleElement::Simple(id, elt) => {
+                let Some(length) = lengths[id as usize] else {
+                    return Ok((elements, Some(id)));
+                };
+                let elt = elt.read_raw(length)
+                    .map_err(|e| format!("failed to read element {id}: {e:?}"))?;
+                elements.push(DecodedElement { id, reply_to: None, request_id: elt.request_id, data: elt.element });
+            }
+            BundleElement::Reply(request_id, elt) => {
+                let elt = elt.read(&RawElementCodec::<RawElementCodecLenVar32>::new())
+                    .map_err(|e| format!("failed to read reply: {e:?}"))?;
+                elements.push(DecodedElement { id: 0xFF, reply_to: Some(request_id), request_id: elt.request_id, data: elt.element });
+            }
+        }
+    }
+
+    Ok((elements, None))
+
+}
+
+/// Internal function to parse the name of a variable length.
+fn parse_length(name: &str) -> Option<ElementLength> {
+    Some(match name {
+        "var8" => ElementLength::Variable8,
+        "var16" => ElementLength::Variable16,
+        "var24" => ElementLength::Variable24,
+        "var32" => ElementLength::Variable32,
+        _ => return None,
+    })
+}
+
+/// Internal function to load a packet from a received datagram.
+fn load_packet(data: &[u8], has_prefix: bool) -> Result<Box<Packet>, String> {
+    if data.len() > PACKET_MAX_LEN {
+        return Err("packet is too long".to_string());
+    }
+    let mut packet = Packet::new_boxed(has_prefix);
+    packet.get_raw_data_mut()[..data.len()].copy_from_slice(data);
+    packet.sync_state(data.len())
+        .map_err(|e| format!("invalid packet: {e:?}"))?;
+    Ok(packet)
+}
+
+fn pxml_element_to_py<'py>(py: Python<'py>, elt: &Element) -> PyResult<Bound<'py, PyAny>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("value", pxml_value_to_py(py, &elt.value)?)?;
+    let children = PyList::empty_bound(py);
+    for (name, value) in elt.iter_children_all() {
+        children.append((name, pxml_value_to_py(py, value)?))?;
+    }
+    dict.set_item("children", children)?;
+    Ok(dict.into_any())
+}
+
+fn pxml_value_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Element(elt) => pxml_element_to_py(py, elt)?,
+        Value::String(s) => s.into_py(py).into_bound(py),
+        Value::Integer(n) => n.into_py(py).into_bound(py),
+        Value::Boolean(b) => b.into_py(py).into_bound(py),
+        Value::Float(n) => n.into_py(py).into_bound(py),
+        Value::Vec3(v) => v.to_array().into_py(py).into_bound(py),
+        Value::Affine3(a) => a.to_cols_array().into_py(py).into_bound(py),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use wgtk::net::element::{FixedElementCodec, Var16ElementCodec};
+
+    use super::*;
+
+    /// Create the datagrams of a bundle with a fixed ping element, a request
+    /// and a reply.
+    fn datagrams() -> Vec<Vec<u8>> {
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x00, &FixedElementCodec::<1>::new(), vec![7]);
+        bundle.add_request(0x01, &Var16ElementCodec::new(), vec![1, 2, 3], 5);
+        bundle.add_reply(&Var16ElementCodec::new(), vec![9, 9], 3);
+        bundle.finalize(&mut 0);
+        bundle.get_packets().iter()
+            .map(|packet| packet.get_raw_data()[..packet.raw_len()].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn packet_load() {
+        let data = &datagrams()[0];
+        let packet = load_packet(data, false).unwrap();
+        assert!(packet.has_requests());
+        assert!(load_packet(&data[..1], false).is_err());
+        assert!(load_packet(&vec![0; PACKET_MAX_LEN + 1], false).is_err());
+    }
+
+    #[test]
+    fn elements_decode() {
+
+        let mut lengths = [None; 256];
+        lengths[0x00] = Some(ElementLength::Fixed(1));
+        lengths[0x01] = parse_length("var16");
+
+        let (elements, unknown_id) = decode_elements(&datagrams(), false, &lengths).unwrap();
+        assert_eq!(unknown_id, None);
+        assert_eq!(elements, [
+            DecodedElement { id: 0x00, reply_to: None, request_id: None, data: vec![7] },
+            DecodedElement { id: 0x01, reply_to: None, request_id: Some(5), data: vec![1, 2, 3] },
+            DecodedElement { id: 0xFF, reply_to: Some(3), request_id: None, data: vec![9, 9] },
+        ]);
+
+        // Decoding stops on the element with an unknown length.
+        lengths[0x01] = None;
+        let (elements, unknown_id) = decode_elements(&datagrams(), false, &lengths).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(unknown_id, Some(0x01));
+
+        // The ping is too short to be read with a larger fixed length.
+        lengths[0x00] = Some(ElementLength::Fixed(1000));
+        assert!(decode_elements(&datagrams(), false, &lengths).is_err());
+
+    }
+
+    #[test]
+    fn length_names() {
+        assert_eq!(parse_length("var8"), Some(ElementLength::Variable8));
+        assert_eq!(parse_length("var32"), Some(ElementLength::Variable32));
+        assert_eq!(parse_length("fixed"), None);
+    }
+
+}
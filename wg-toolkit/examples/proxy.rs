@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::env;
+use std::fs::File;
 use std::rc::Rc;
 
 use rsa::{RsaPrivateKey, RsaPublicKey, pkcs8::{FromPublicKey, FromPrivateKey}, PublicKeyParts};
@@ -8,6 +9,8 @@ use rsa::{RsaPrivateKey, RsaPublicKey, pkcs8::{FromPublicKey, FromPrivateKey}, P
 use wgtk::net::proxy::{Proxy, ProxyListener, ProxyDirectTransfer, ProxySideOutput};
 use wgtk::net::bundle::{Bundle, BundleElement, BundleAssembler};
 use wgtk::net::packet::Packet;
+use wgtk::net::pool::PacketPool;
+use wgtk::net::keylog::KeyLogWriter;
 
 use wgtk::net::element::login::{LoginCodec, PingCodec, ChallengeCodec, ChallengeResponseCodec};
 use wgtk::net::element::reply::{ReplyCodec};
@@ -33,14 +36,16 @@ fn main() {
     println!("server pubkey size: {}", server_pubkey.size());
 
     let reply_tracker = RefCell::new(RequestTracker::default());
+    // Packets of assembled bundles are returned to the proxy's pool once dropped.
+    let pool = PacketPool::default();
 
     let mut login_proxy = Proxy::bind(
         client_bind_addr,
         server_bind_addr,
         server_addr,
-        LoginAppClientListener::new(&server_pubkey, &client_privkey, &reply_tracker),
-        LoginAppServerListener::new(&reply_tracker)
-    ).unwrap();
+        LoginAppClientListener::new(&server_pubkey, &client_privkey, &reply_tracker, pool.clone()),
+        LoginAppServerListener::new(&reply_tracker, pool.clone())
+    ).unwrap().with_pool(pool);
 
     loop {
         login_proxy.poll().unwrap();
@@ -52,15 +57,17 @@ fn main() {
 struct LoginAppClientListener<'ek, 'dk, 'rt> {
     asm: BundleAssembler,
     login_codec: LoginCodec<'ek, 'dk>,
-    reply_tracker: &'rt RefCell<RequestTracker>
+    reply_tracker: &'rt RefCell<RequestTracker>,
+    keylog: Option<KeyLogWriter<File>>,
 }
 
 impl<'ek, 'dk, 'rt> LoginAppClientListener<'ek, 'dk, 'rt> {
-    pub fn new(server_pubkey: &'ek RsaPublicKey, client_privkey: &'dk RsaPrivateKey, reply_tracker: &'rt RefCell<RequestTracker>) -> Self {
+    pub fn new(server_pubkey: &'ek RsaPublicKey, client_privkey: &'dk RsaPrivateKey, reply_tracker: &'rt RefCell<RequestTracker>, pool: PacketPool) -> Self {
         Self {
-            asm: BundleAssembler::new(true),
+            asm: BundleAssembler::new(true).with_pool(pool),
             login_codec: LoginCodec::new_encrypted(server_pubkey, client_privkey),
-            reply_tracker
+            reply_tracker,
+            keylog: KeyLogWriter::from_env().unwrap(),
         }
     }
 }
@@ -88,6 +95,9 @@ impl ProxyListener for LoginAppClientListener<'_, '_, '_> {
                         BundleElement::Simple(LoginCodec::ID, reader) => {
                             let login = reader.read(&self.login_codec).unwrap();
                             println!("[CLIENT -> SERVER] Received login: {:?}", login.element);
+                            if let Some(keylog) = &mut self.keylog {
+                                keylog.write_blowfish_key(&login.element.username, &login.element.blowfish_key).unwrap();
+                            }
                             let request_id = login.request_id.unwrap();
                             let mut new_bundle = Bundle::new_empty(true);
                             new_bundle.add_request(LoginCodec::ID, &self.login_codec, login.element, request_id);
@@ -129,9 +139,9 @@ struct LoginAppServerListener<'rt> {
 }
 
 impl<'rt> LoginAppServerListener<'rt> {
-    pub fn new(reply_tracker: &'rt RefCell<RequestTracker>) -> Self {
+    pub fn new(reply_tracker: &'rt RefCell<RequestTracker>, pool: PacketPool) -> Self {
         Self {
-            asm: BundleAssembler::new(true),
+            asm: BundleAssembler::new(true).with_pool(pool),
             reply_tracker
         }
     }
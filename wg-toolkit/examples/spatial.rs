@@ -0,0 +1,87 @@
+//! Benchmark of the spatial grid with thousands of moving entities, each
+//! entity computing its area of interest every tick like a client would.
+//!
+//! Run with `cargo run --release --example spatial [entities] [ticks]`.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use std::env;
+
+use glam::Vec3;
+
+use wgtk::util::spatial::SpatialGrid;
+
+
+/// Size of the square space where entities are moving.
+const SPACE_SIZE: f32 = 2000.0;
+/// Radius of the area of interest.
+const AOI_RADIUS: f32 = 100.0;
+/// Maximum distance moved by an entity each tick.
+const SPEED: f32 = 5.0;
+
+
+fn main() {
+
+    let mut args = env::args().skip(1);
+    let entity_count: u32 = args.next().map(|arg| arg.parse().unwrap()).unwrap_or(5000);
+    let tick_count: u32 = args.next().map(|arg| arg.parse().unwrap()).unwrap_or(100);
+
+    let mut rng = XorShift(0x9E3779B97F4A7C15);
+    let mut grid = SpatialGrid::new(AOI_RADIUS);
+    let mut positions = Vec::with_capacity(entity_count as usize);
+    let mut sets = vec![HashSet::new(); entity_count as usize];
+
+    for key in 0..entity_count {
+        let pos = Vec3::new(rng.next_f32() * SPACE_SIZE, 0.0, rng.next_f32() * SPACE_SIZE);
+        grid.insert(key, pos);
+        positions.push(pos);
+    }
+
+    let mut move_time = Duration::ZERO;
+    let mut query_time = Duration::ZERO;
+    let mut delta_count = 0usize;
+
+    for _ in 0..tick_count {
+
+        let start = Instant::now();
+        for (key, pos) in positions.iter_mut().enumerate() {
+            pos.x = (pos.x + (rng.next_f32() * 2.0 - 1.0) * SPEED).clamp(0.0, SPACE_SIZE);
+            pos.z = (pos.z + (rng.next_f32() * 2.0 - 1.0) * SPEED).clamp(0.0, SPACE_SIZE);
+            grid.insert(key as u32, *pos);
+        }
+        move_time += start.elapsed();
+
+        let start = Instant::now();
+        for (pos, set) in positions.iter().zip(&mut sets) {
+            let delta = grid.query_delta(*pos, AOI_RADIUS, set);
+            delta_count += delta.entered.len() + delta.left.len();
+        }
+        query_time += start.elapsed();
+
+    }
+
+    println!("entities: {entity_count}, ticks: {tick_count}, deltas: {delta_count}");
+    println!("move:  {:?}/tick", move_time / tick_count);
+    println!("query: {:?}/tick", query_time / tick_count);
+
+    // A radius much larger than the cells is bounded by non-empty cells.
+    let start = Instant::now();
+    let count = grid.query_radius(Vec3::ZERO, 1e9).count();
+    println!("large radius query: {count} entities in {:?}", start.elapsed());
+
+}
+
+
+/// Minimal deterministic random generator, to avoid any dependency.
+struct XorShift(u64);
+
+impl XorShift {
+
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+}
@@ -0,0 +1,224 @@
+//! Compact descriptors, the binary identifiers of items used in entities'
+//! properties and methods.
+//! 
+//! Items are identified by their type, their nation and their identifier
+//! within the nation. Components such as guns or shells use an integer
+//! descriptor, vehicles use a bytes descriptor starting with a header and
+//! followed by their components.
+
+use thiserror::Error;
+
+
+/// Type of an item, as stored in the 4 low bits of a descriptor's header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ItemType {
+    Vehicle = 1,
+    VehicleChassis = 2,
+    VehicleTurret = 3,
+    VehicleGun = 4,
+    VehicleEngine = 5,
+    VehicleFuelTank = 6,
+    VehicleRadio = 7,
+    Tankman = 8,
+    OptionalDevice = 9,
+    Shell = 10,
+    Equipment = 11,
+}
+
+impl ItemType {
+
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        Some(match raw {
+            1 => Self::Vehicle,
+            2 => Self::VehicleChassis,
+            3 => Self::VehicleTurret,
+            4 => Self::VehicleGun,
+            5 => Self::VehicleEngine,
+            6 => Self::VehicleFuelTank,
+            7 => Self::VehicleRadio,
+            8 => Self::Tankman,
+            9 => Self::OptionalDevice,
+            10 => Self::Shell,
+            11 => Self::Equipment,
+            _ => return None
+        })
+    }
+
+}
+
+
+/// Maximum number of nations, because the nation is stored in the 4 high bits
+/// of a descriptor's header.
+pub const NATIONS_COUNT: u8 = 16;
+
+
+/// Internal function to compute a descriptor's header.
+#[inline]
+fn make_header(item_type: ItemType, nation: u8) -> Result<u8, EncodeError> {
+    if nation >= NATIONS_COUNT {
+        return Err(EncodeError::InvalidNation(nation));
+    }
+    Ok((item_type as u8) | (nation << 4))
+}
+
+/// Internal function to split a descriptor's header.
+#[inline]
+fn parse_header(header: u8) -> Result<(ItemType, u8), DecodeError> {
+    let raw_type = header & 0x0F;
+    let item_type = ItemType::from_raw(raw_type).ok_or(DecodeError::InvalidItemType(raw_type))?;
+    Ok((item_type, header >> 4))
+}
+
+
+/// An integer compact descriptor, used for vehicles' components and other
+/// items that are not vehicles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntCompactDescr {
+    pub item_type: ItemType,
+    /// Identifier of the nation, in the 4 high bits of the header.
+    pub nation: u8,
+    /// Identifier of the item within its nation and type.
+    pub id: u16,
+}
+
+impl IntCompactDescr {
+
+    pub fn new(item_type: ItemType, nation: u8, id: u16) -> Self {
+        Self { item_type, nation, id }
+    }
+
+    /// Decode an integer compact descriptor.
+    pub fn decode(raw: u32) -> Result<Self, DecodeError> {
+        let (item_type, nation) = parse_header(raw as u8)?;
+        let id = u16::try_from(raw >> 8).map_err(|_| DecodeError::InvalidItemId)?;
+        Ok(Self { item_type, nation, id })
+    }
+
+    /// Encode this integer compact descriptor.
+    pub fn encode(&self) -> Result<u32, EncodeError> {
+        Ok(((self.id as u32) << 8) | make_header(self.item_type, self.nation)? as u32)
+    }
+
+}
+
+
+/// A vehicle compact descriptor. Only the vehicle's type is decoded, its
+/// components and other customizations are kept raw.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VehicleCompactDescr {
+    /// Identifier of the nation.
+    pub nation: u8,
+    /// Identifier of the vehicle within its nation.
+    pub vehicle_id: u8,
+    /// Raw components following the vehicle's type.
+    pub components: Vec<u8>,
+}
+
+impl VehicleCompactDescr {
+
+    /// Decode a vehicle compact descriptor.
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        let [header, vehicle_id, ref components @ ..] = *data else {
+            return Err(DecodeError::TooShort);
+        };
+        match parse_header(header)? {
+            (ItemType::Vehicle, nation) => Ok(Self {
+                nation,
+                vehicle_id,
+                components: components.to_vec(),
+            }),
+            (item_type, _) => Err(DecodeError::UnexpectedItemType(item_type)),
+        }
+    }
+
+    /// Encode this vehicle compact descriptor.
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut data = Vec::with_capacity(2 + self.components.len());
+        data.push(make_header(ItemType::Vehicle, self.nation)?);
+        data.push(self.vehicle_id);
+        data.extend_from_slice(&self.components);
+        Ok(data)
+    }
+
+    /// Get the integer compact descriptor of this vehicle's type, which is
+    /// used to identify the vehicle type without its components.
+    pub fn type_descr(&self) -> IntCompactDescr {
+        IntCompactDescr::new(ItemType::Vehicle, self.nation, self.vehicle_id as u16)
+    }
+
+}
+
+
+/// Errors that can happen while decoding a compact descriptor.
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    /// The header has an unknown item type.
+    #[error("invalid item type {0}")]
+    InvalidItemType(u8),
+    /// The item type is not the expected one.
+    #[error("unexpected item type {0:?}")]
+    UnexpectedItemType(ItemType),
+    /// The item identifier doesn't fit in 16 bits.
+    #[error("invalid item id")]
+    InvalidItemId,
+    /// The descriptor is too short.
+    #[error("descriptor too short")]
+    TooShort,
+}
+
+/// Errors that can happen while encoding a compact descriptor.
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    /// The nation doesn't fit in the 4 high bits of the header.
+    #[error("invalid nation {0}")]
+    InvalidNation(u8),
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn int_descr() {
+
+        // Vehicle 11 of nation 2.
+        let descr = IntCompactDescr::decode(0x0B21).unwrap();
+        assert_eq!(descr, IntCompactDescr::new(ItemType::Vehicle, 2, 11));
+        assert_eq!(descr.encode().unwrap(), 0x0B21);
+
+        // Shell 0x1234 of nation 3.
+        let descr = IntCompactDescr::decode(0x12343A).unwrap();
+        assert_eq!(descr, IntCompactDescr::new(ItemType::Shell, 3, 0x1234));
+        assert_eq!(descr.encode().unwrap(), 0x12343A);
+
+        assert!(matches!(IntCompactDescr::decode(0x0B2F), Err(DecodeError::InvalidItemType(15))));
+        assert!(matches!(IntCompactDescr::decode(0x01000021), Err(DecodeError::InvalidItemId)));
+        assert!(matches!(IntCompactDescr::new(ItemType::Shell, 16, 1).encode(), Err(EncodeError::InvalidNation(16))));
+
+    }
+
+    #[test]
+    fn vehicle_descr() {
+
+        let data = [0x11, 0x05, 0xAA, 0xBB];
+        let descr = VehicleCompactDescr::decode(&data).unwrap();
+        assert_eq!(descr.nation, 1);
+        assert_eq!(descr.vehicle_id, 5);
+        assert_eq!(descr.components, [0xAA, 0xBB]);
+        assert_eq!(descr.type_descr().encode().unwrap(), 0x0511);
+        assert_eq!(descr.encode().unwrap(), data);
+
+        assert!(matches!(VehicleCompactDescr::decode(&[0x11]), Err(DecodeError::TooShort)));
+        assert!(matches!(VehicleCompactDescr::decode(&[0x14, 0x05]), Err(DecodeError::UnexpectedItemType(ItemType::VehicleGun))));
+
+        let descr = VehicleCompactDescr { nation: 0xFF, vehicle_id: 5, components: Vec::new() };
+        assert!(matches!(descr.encode(), Err(EncodeError::InvalidNation(0xFF))));
+
+    }
+
+}
@@ -0,0 +1,25 @@
+//! Readers for gameplay items definitions, found in the packed XML files of
+//! the "scripts/item_defs/" resources directory.
+
+use crate::pxml::Value;
+
+pub mod vehicle;
+pub mod shell;
+pub mod compact_descr;
+
+
+/// Internal function to get a number from an integer or float value.
+fn value_as_f32(value: &Value) -> Option<f32> {
+    match *value {
+        Value::Integer(n) => Some(n as f32),
+        Value::Float(n) => Some(n),
+        _ => None
+    }
+}
+
+/// Internal function to split a space-separated tags string.
+fn split_tags(value: &Value) -> Vec<String> {
+    value.as_string()
+        .map(|tags| tags.split_ascii_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,101 @@
+//! Reader for nations' shells, "item_defs/vehicles/<nation>/components/shells.xml".
+
+use std::io::{Seek, Read};
+
+use thiserror::Error;
+
+use crate::pxml::{self, Value, Element};
+
+use super::value_as_f32;
+
+
+/// Try to read a nation's shells from a seekable reader.
+/// 
+/// *The content will be read starting from the inital position
+/// of the reader.*
+pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Vec<Shell>, DeError> {
+    let root_elt = pxml::from_reader(reader)?;
+    from_element(&root_elt)
+}
+
+/// Read a nation's shells from its packed XML root element, children that
+/// are not shells (such as icons) are ignored.
+pub fn from_element(root_elt: &Element) -> Result<Vec<Shell>, DeError> {
+
+    let mut shells = Vec::new();
+
+    for (name, value) in root_elt.iter_children_all() {
+
+        let Value::Element(shell_elt) = value else { continue };
+        // Only shells have an identifier.
+        let Some(id) = shell_elt.get_child("id") else { continue };
+
+        let invalid = || DeError::InvalidShell(name.clone());
+
+        let id = id.as_integer()
+            .and_then(|id| u16::try_from(id).ok())
+            .ok_or_else(invalid)?;
+
+        let kind = shell_elt.get_child("kind")
+            .and_then(Value::as_string)
+            .ok_or_else(invalid)?
+            .trim()
+            .to_string();
+
+        let caliber = shell_elt.get_child("caliber")
+            .and_then(value_as_f32)
+            .ok_or_else(invalid)?;
+
+        let damage_elt = shell_elt.get_child("damage").and_then(Value::as_element);
+
+        shells.push(Shell {
+            name: name.clone(),
+            id,
+            user_string: shell_elt.get_child("userString").and_then(Value::as_string).cloned(),
+            kind,
+            caliber,
+            armor_damage: damage_elt.and_then(|elt| elt.get_child("armor")).and_then(value_as_f32),
+            devices_damage: damage_elt.and_then(|elt| elt.get_child("devices")).and_then(value_as_f32),
+            tracer: shell_elt.get_child("isTracer").and_then(Value::as_boolean).unwrap_or(false),
+        });
+
+    }
+
+    Ok(shells)
+
+}
+
+
+/// A shell definition.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shell {
+    /// Name of the shell.
+    pub name: String,
+    /// Identifier of the shell within its nation.
+    pub id: u16,
+    /// Localization key of the shell's name.
+    pub user_string: Option<String>,
+    /// Kind of shell, such as "ARMOR_PIERCING" or "HIGH_EXPLOSIVE".
+    pub kind: String,
+    /// Caliber in millimeters.
+    pub caliber: f32,
+    /// Damage dealt to the vehicle's health.
+    pub armor_damage: Option<f32>,
+    /// Damage dealt to modules and crew.
+    pub devices_damage: Option<f32>,
+    /// The shell has a tracer.
+    pub tracer: bool,
+}
+
+
+/// Errors that can happend while deserializing shells.
+#[derive(Debug, Error)]
+pub enum DeError {
+    /// A shell has a missing or invalid identifier, kind or caliber.
+    #[error("invalid shell: {0}")]
+    InvalidShell(String),
+    /// Underlying Packed XML deserialization error.
+    #[error("pxml error: {0}")]
+    Pxml(#[from] pxml::DeError),
+}
@@ -0,0 +1,113 @@
+//! Reader for nations' vehicle lists, "item_defs/vehicles/<nation>/list.xml".
+
+use std::io::{Seek, Read};
+
+use thiserror::Error;
+
+use crate::pxml::{self, Value, Element};
+
+use super::split_tags;
+
+
+/// Try to read a nation's vehicle list from a seekable reader.
+/// 
+/// *The content will be read starting from the inital position
+/// of the reader.*
+pub fn from_reader<R: Read + Seek>(reader: R) -> Result<VehicleList, DeError> {
+    let root_elt = pxml::from_reader(reader)?;
+    from_element(&root_elt)
+}
+
+/// Read a nation's vehicle list from its packed XML root element, children
+/// that are not vehicles are ignored.
+pub fn from_element(root_elt: &Element) -> Result<VehicleList, DeError> {
+
+    let mut vehicles = Vec::new();
+
+    for (name, value) in root_elt.iter_children_all() {
+
+        let Value::Element(vehicle_elt) = value else { continue };
+        // Only vehicles have an identifier.
+        let Some(id) = vehicle_elt.get_child("id") else { continue };
+
+        let id = id.as_integer()
+            .and_then(|id| u16::try_from(id).ok())
+            .ok_or_else(|| DeError::InvalidVehicleId(name.clone()))?;
+
+        vehicles.push(VehicleListEntry {
+            name: name.clone(),
+            id,
+            user_string: vehicle_elt.get_child("userString").and_then(Value::as_string).cloned(),
+            short_user_string: vehicle_elt.get_child("shortUserString").and_then(Value::as_string).cloned(),
+            level: vehicle_elt.get_child("level").and_then(Value::as_integer).unwrap_or(0) as u8,
+            tags: vehicle_elt.get_child("tags").map(split_tags).unwrap_or_default(),
+        });
+
+    }
+
+    Ok(VehicleList { vehicles })
+
+}
+
+
+/// The list of all vehicles of a nation.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VehicleList {
+    pub vehicles: Vec<VehicleListEntry>,
+}
+
+/// A vehicle in a nation's list.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VehicleListEntry {
+    /// Name of the vehicle, such as "R04_T-34".
+    pub name: String,
+    /// Identifier of the vehicle within its nation, this is the identifier
+    /// used in compact descriptors.
+    pub id: u16,
+    /// Localization key of the vehicle's name.
+    pub user_string: Option<String>,
+    /// Localization key of the vehicle's short name.
+    pub short_user_string: Option<String>,
+    /// Tier of the vehicle, zero if not specified.
+    pub level: u8,
+    /// Tags of the vehicle, including its class such as "mediumTank".
+    pub tags: Vec<String>,
+}
+
+impl VehicleList {
+
+    /// Get a vehicle from its identifier within the nation.
+    pub fn get_by_id(&self, id: u16) -> Option<&VehicleListEntry> {
+        self.vehicles.iter().find(|vehicle| vehicle.id == id)
+    }
+
+    /// Get a vehicle from its name.
+    pub fn get_by_name(&self, name: &str) -> Option<&VehicleListEntry> {
+        self.vehicles.iter().find(|vehicle| vehicle.name == name)
+    }
+
+}
+
+impl VehicleListEntry {
+
+    /// Return true if the vehicle has the given tag.
+    #[inline]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+}
+
+
+/// Errors that can happend while deserializing a vehicle list.
+#[derive(Debug, Error)]
+pub enum DeError {
+    /// A vehicle has an invalid identifier.
+    #[error("invalid identifier for vehicle: {0}")]
+    InvalidVehicleId(String),
+    /// Underlying Packed XML deserialization error.
+    #[error("pxml error: {0}")]
+    Pxml(#[from] pxml::DeError),
+}
@@ -13,6 +13,7 @@ pub mod res;
 
 pub mod space;
 pub mod model;
+pub mod item;
 
 #[cfg(feature = "network")]
 pub mod net;
@@ -56,6 +56,7 @@ where
 
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Model {
     /// Description of the visual components of the model.
     pub visual: Box<Visual>,
@@ -64,6 +65,7 @@ pub struct Model {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RenderSetData {
     /// All vertices for the model. To access correct vertices,
     /// use correct method of the model to get access to them.
@@ -127,6 +127,7 @@ impl<R: Read + Seek> PrimitiveReader<R> {
 
 /// Metadata about a section in the primitive file.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SectionMeta {
     pub name: String,
     pub off: usize,
@@ -136,6 +137,7 @@ pub struct SectionMeta {
 
 /// A section that contains vertices.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertices {
     pub vertices: Vec<Vertex>,
 }
@@ -292,6 +294,7 @@ impl Section for Vertices {
 
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertex {
     pub position: Vec3,
     pub normal: Vec3,
@@ -320,6 +323,7 @@ impl fmt::Debug for Vertex {
 
 /// A section that contains indices and groups.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Indices {
     /// Listing of all primitives (triangles).
     pub primitives: Vec<Primitive>,
@@ -381,6 +385,7 @@ impl Section for Indices {
 
 /// A primitive (triangle) of indices, referencing vertices.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Primitive {
     pub a: u32,
     pub b: u32,
@@ -388,6 +393,7 @@ pub struct Primitive {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Group {
     /// Offset of the first primitive of this group.
     pub primitives_offset: u32,
@@ -174,6 +174,7 @@ fn read_render_set(element: &Element) -> Option<RenderSet> {
 
 /// Represent an entire visual processed file.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Visual {
     /// The root node.
     pub root_node: Node,
@@ -191,6 +192,7 @@ pub struct Visual {
 
 /// Represent a node in the visual tree.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     /// Identifier of the node.
     pub identifier: String,
@@ -202,6 +204,7 @@ pub struct Node {
 
 /// Represent a render set for a model's visual.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RenderSet {
     /// Name of the target node for this render set.
     pub node: String,
@@ -213,6 +216,7 @@ pub struct RenderSet {
 
 /// Represent the geometry of a render set.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Geometry {
     /// Identifier of the vertices section in the primitive binary file.
     pub vertices_section: String,
@@ -224,6 +228,7 @@ pub struct Geometry {
 
 /// Prititive group of a geometry.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrimitiveGroup {
     /// Index of the primitive group.
     pub index: u32,
@@ -234,6 +239,7 @@ pub struct PrimitiveGroup {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Material {
     pub identifier: String,
     pub properties: HashMap<String, MaterialProperty>,
@@ -243,6 +249,7 @@ pub struct Material {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MaterialProperty {
     /// Integer property.
     Texture(String),
@@ -10,9 +10,10 @@ use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 
 use super::packet::{Packet, PACKET_MAX_BODY_LEN, PACKET_FLAGS_LEN};
 use super::element::reply::{ReplyHeaderCodec, ReplyCodec, Reply, REPLY_ID};
-use super::element::ElementCodec;
+use super::element::{ElementCodec, ElementLength, ElementLengthTable};
+use super::pool::PacketPool;
 
-use crate::util::SubCursor;
+use crate::util::cursor::SubCursor;
 
 
 pub const BUNDLE_FRAGMENT_MAX_AGE: Duration = Duration::from_secs(10);
@@ -31,8 +32,13 @@ pub struct Bundle {
     available_len: usize,
     /// If packets in this bundle has a prefix.
     has_prefix: bool,
+    /// If new packets in this bundle have a checksum.
+    has_checksum: bool,
     /// Offset of the link of the last request, `0` if not request yet.
     last_request_header_offset: usize,
+    /// Optional pool used to acquire new packets and to release all packets
+    /// when the bundle is dropped.
+    pool: Option<PacketPool>,
     // /// Offsets to all requests' headers in this bundle, it's used to add replay IDs.
     // /// Each tuple in the vec are of the form `(packet_index, request_header_offset)`.
     // request_header_offsets: Vec<(usize, usize)>
@@ -48,7 +54,9 @@ impl Bundle {
             packets,
             force_new_packet: true,
             has_prefix,
+            has_checksum: false,
             last_request_header_offset: 0,
+            pool: None,
             // request_header_offsets: Vec::new()
         }
     }
@@ -69,6 +77,23 @@ impl Bundle {
         Self::new(packets, has_prefix)
     }
 
+    /// Use the given pool for acquiring new packets, all packets of this
+    /// bundle are released to this pool when the bundle is dropped.
+    pub fn with_pool(mut self, pool: PacketPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Enable or disable the checksum footer of all packets of this bundle,
+    /// including packets added later. The checksum is computed when the
+    /// bundle is finalized.
+    pub fn set_checksum(&mut self, enabled: bool) {
+        self.has_checksum = enabled;
+        for packet in &mut self.packets {
+            packet.set_checksum(enabled);
+        }
+    }
+
     /// Add a basic element to this bundle.
     #[inline]
     pub fn add_element<E: ElementCodec>(&mut self, id: u8, codec: &E, elt: E::Element) {
@@ -194,7 +219,11 @@ impl Bundle {
 
     /// Internal method to add a new packet at the end of the chain.
     fn add_packet(&mut self) {
-        let packet = Packet::new_boxed(self.has_prefix);
+        let mut packet = match &self.pool {
+            Some(pool) => pool.acquire(self.has_prefix),
+            None => Packet::new_boxed(self.has_prefix),
+        };
+        packet.set_checksum(self.has_checksum);
         self.available_len = packet.available_len();
         self.packets.push(packet);
         self.last_request_header_offset = 0;
@@ -230,6 +259,14 @@ impl Bundle {
 
 }
 
+impl Drop for Bundle {
+    fn drop(&mut self) {
+        if let Some(pool) = &self.pool {
+            pool.release_all(self.packets.drain(..));
+        }
+    }
+}
+
 
 /// An internal writer implementation used to append data to a bundle,
 /// adding packets if needed.
@@ -361,8 +398,9 @@ impl<'a> BundleReader<'a> {
                     self.packet_body_pos += rel_pos as usize;
                 } else {
                     // We are after the current packet.
+                    let remaining = self.packet_body.len() as u64;
                     self.packets = &self.packets[1..];
-                    self.seek_relative_unchecked(rel_pos as u64);
+                    self.seek_relative_unchecked(rel_pos as u64 - remaining);
                 }
             } else if rel_pos < 0 {
                 if rel_pos >= -(self.packet_body_pos as i64) {
@@ -409,6 +447,7 @@ impl<'a> Read for BundleReader<'a> {
             let len = buf.len().min(self.packet_body.len());
             buf[..len].copy_from_slice(&self.packet_body[..len]);
             self.packet_body = &self.packet_body[len..];
+            self.packet_body_pos += len;
             self.pos += len as u64;
             if self.packet_body.is_empty() {
                 self.packets = &self.packets[1..];
@@ -490,33 +529,131 @@ impl<'bundle> BundleElementReader<'bundle> {
         }
     }
 
+    /// Return an iterator decoding each element with the given function, which should
+    /// read the element it's given (with `read`, not `read_stable`) in order to go to
+    /// the next element. The iterator stops after the first error, and an error is
+    /// also returned if an element has not been read.
+    /// 
+    /// This allows using iterator adapters and collecting elements, which is not
+    /// possible with `next_element` because each element borrows this reader.
+    pub fn decode_elements<F, T, E>(&mut self, decode: F) -> DecodeElements<'_, 'bundle, F>
+    where
+        F: FnMut(BundleElement<'_, 'bundle>) -> Result<T, E>,
+        E: From<ReadElementError>,
+    {
+        DecodeElements { reader: self, decode, done: false }
+    }
+
+    /// Call the given function for each element until it returns an error, the 
+    /// function should read the element it's given, see `decode_elements`.
+    pub fn try_for_each_element<F, E>(&mut self, func: F) -> Result<(), E>
+    where
+        F: FnMut(BundleElement<'_, 'bundle>) -> Result<(), E>,
+        E: From<ReadElementError>,
+    {
+        self.decode_elements(func).collect()
+    }
+
     /// Try to decode the current element using a given codec. You can choose to go
     /// to the next element using the `next` argument.
     pub fn read_element<E>(&mut self, codec: &E, next: bool) -> Result<Element<E::Element>, ReadElementError>
     where
         E: ElementCodec
     {
+        self.read_element_with(E::LEN, next, |read, len| codec.decode(read, len))
+    }
+
+    /// Read the raw data of the current element, given its length policy. You can
+    /// choose to go to the next element using the `next` argument.
+    pub fn read_element_raw(&mut self, length: ElementLength, next: bool) -> Result<Element<Vec<u8>>, ReadElementError> {
+        self.read_element_with(length, next, |mut read, _| {
+            // The buffer is not preallocated from the untrusted element's length.
+            let mut buf = Vec::new();
+            read.read_to_end(&mut buf)?;
+            Ok(buf)
+        })
+    }
+
+    /// Skip the current element given its length policy, this goes to the next element
+    /// and returns the request ID if the element was a request.
+    pub fn skip_element(&mut self, length: ElementLength) -> Result<Option<u32>, ReadElementError> {
+        self.read_element_with(length, true, |_, _| Ok(())).map(|elt| elt.request_id)
+    }
+
+    /// Skip the current element using the length policy found in the given table, replies
+    /// are always skipped. An error is returned if the element is not in the table.
+    pub fn skip_element_with(&mut self, table: &ElementLengthTable) -> Result<Option<u32>, ReadElementError> {
+        let id = self.read_id().ok_or(ReadElementError::TooShortPacket)?;
+        let length = if id == REPLY_ID {
+            ReplyHeaderCodec::LEN
+        } else {
+            table.get_length(id).ok_or(ReadElementError::UnknownElement(id))?
+        };
+        self.skip_element(length)
+    }
+
+    /// Read all the remaining data of the bundle after the current element's identifier,
+    /// this is useful to get samples of elements with an unknown length policy. The
+    /// reader is at the end of the bundle afterward.
+    pub fn read_remaining(&mut self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        // Reading from the bundle can't fail.
+        self.bundle_reader.read_to_end(&mut buf).unwrap();
+        self.next_request_offset = 0;
+        if !buf.is_empty() {
+            buf.remove(0);
+        }
+        buf
+    }
+
+    /// Try to decode the current element using a given codec, if the element can't be
+    /// decoded, it is skipped using its length header and an error is returned, so the
+    /// rest of the bundle can still be read.
+    pub fn read_element_lenient<E>(&mut self, codec: &E) -> Result<Element<E::Element>, ElementError>
+    where
+        E: ElementCodec
+    {
+        let id = self.read_id().unwrap_or(0);
+        match self.read_element(codec, true) {
+            Ok(elt) => Ok(elt),
+            Err(error) => {
+                let skipped = self.skip_element(E::LEN).is_ok();
+                Err(ElementError { id, error, skipped })
+            }
+        }
+    }
+
+    /// Internal function to read the current element given its length policy and a
+    /// decoding function.
+    fn read_element_with<T, F>(&mut self, length: ElementLength, next: bool, decode: F) -> Result<Element<T>, ReadElementError>
+    where
+        F: FnOnce(SubCursor<&mut BundleReader<'bundle>>, u64) -> io::Result<T>
+    {
 
         let request = self.is_request();
-        let header_len = E::LEN.len() + 1 + if request { 6 } else { 0 };
+        let header_len = length.len() + 1 + if request { 6 } else { 0 };
 
         if self.bundle_reader.get_packet_remaining_data().len() < header_len {
             return Err(ReadElementError::TooShortPacket);
         }
 
         // We store the starting position of the element, it will be used if we need to rollback.
+        // The next request offset is also restored because it's updated when reading requests.
         let elt_pos = self.bundle_reader.pos();
+        let next_request_offset = self.next_request_offset;
 
-        match self.read_element_internal(codec, next, request) {
+        match self.read_element_internal(length, next, request, decode) {
             Ok(elt) if next => Ok(elt),
             Ok(elt) => {
                 // If no error but we don't want to go next.
                 self.bundle_reader.seek_absolute(elt_pos);
+                self.next_request_offset = next_request_offset;
                 Ok(elt)
             }
             Err(e) => {
                 // If any error happens, we cancel the operation.
                 self.bundle_reader.seek_absolute(elt_pos);
+                self.next_request_offset = next_request_offset;
                 Err(ReadElementError::Io(e))
             }
         }
@@ -525,15 +662,15 @@ impl<'bundle> BundleElementReader<'bundle> {
 
     /// Internal only. Used by `next` to wrap all IO errors and reset seek if an error happens.
     #[inline(always)]
-    fn read_element_internal<E>(&mut self, codec: &E, next: bool, request: bool) -> io::Result<Element<E::Element>>
+    fn read_element_internal<T, F>(&mut self, length: ElementLength, next: bool, request: bool, decode: F) -> io::Result<Element<T>>
     where
-        E: ElementCodec
+        F: FnOnce(SubCursor<&mut BundleReader<'bundle>>, u64) -> io::Result<T>
     {
 
         let start_packet = self.bundle_reader.get_packet().unwrap();
 
         let _elt_id = self.bundle_reader.read_u8()?;
-        let elt_len = E::LEN.read(&mut self.bundle_reader)? as u64;
+        let elt_len = length.read(&mut self.bundle_reader)? as u64;
 
         let reply_id = if request {
             let reply_id = self.bundle_reader.read_u32::<LE>()?;
@@ -546,6 +683,11 @@ impl<'bundle> BundleElementReader<'bundle> {
         let elt_data_begin = self.bundle_reader.pos();
         let elt_data_end = elt_data_begin + elt_len;
 
+        // The element's length must not exceed the bundle.
+        if elt_data_end > self.bundle_reader.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
         // We can use unchecked because we know that the given 'inner' reader
         // is placed at the same position as given 'begin'.
         let elt_data_reader = SubCursor::new_unchecked(
@@ -554,7 +696,7 @@ impl<'bundle> BundleElementReader<'bundle> {
             elt_data_end
         );
 
-        let element = codec.decode(elt_data_reader, elt_len)?;
+        let element = decode(elt_data_reader, elt_len)?;
 
         // We seek to the end only if we want to go next.
         if next {
@@ -587,8 +729,50 @@ impl<'bundle> BundleElementReader<'bundle> {
 }
 
 
+/// An iterator decoding each element of a bundle with a function, returned by
+/// `BundleElementReader::decode_elements`.
+pub struct DecodeElements<'reader, 'bundle, F> {
+    reader: &'reader mut BundleElementReader<'bundle>,
+    decode: F,
+    /// Set after the first error.
+    done: bool,
+}
+
+impl<'bundle, F, T, E> Iterator for DecodeElements<'_, 'bundle, F>
+where
+    F: FnMut(BundleElement<'_, 'bundle>) -> Result<T, E>,
+    E: From<ReadElementError>,
+{
+
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        if self.done {
+            return None;
+        }
+
+        let pos = self.reader.bundle_reader.pos();
+        let element = self.reader.next_element()?;
+        let res = (self.decode)(element);
+
+        let res = match res {
+            // If the element has not been read, we would loop forever on it.
+            Ok(_) if self.reader.bundle_reader.pos() == pos => Err(ReadElementError::NotConsumed.into()),
+            res => res,
+        };
+
+        self.done = res.is_err();
+        Some(res)
+
+    }
+
+}
+
+
 /// An element read from `BundleElementReader` and `BundleElement` variants,
 /// also containing the element's ID and an optional request ID.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Element<E> {
     /// The actual element.
     pub element: E,
@@ -613,10 +797,27 @@ pub enum ReadElementError {
     /// The current packet isn't enough large for element's header,
     /// which need to be on a single packet.
     TooShortPacket,
+    /// The element was not read by the function given to `decode_elements`.
+    NotConsumed,
+    /// The element's identifier is not known, so its length can't be read.
+    UnknownElement(u8),
     /// An unexpected or unhandled IO error happened.
     Io(io::Error)
 }
 
+/// Error returned when an element can't be decoded in lenient mode, see
+/// `BundleElementReader::read_element_lenient`.
+#[derive(Debug)]
+pub struct ElementError {
+    /// Identifier of the element.
+    pub id: u8,
+    /// The decoding error.
+    pub error: ReadElementError,
+    /// True if the element has been skipped and the next element can be read,
+    /// false if the element's header itself can't be read.
+    pub skipped: bool,
+}
+
 
 /// Bundle element variant iterated from `BundleElementIter`.
 /// This enum provides a better way to read replies using sub codecs.
@@ -659,6 +860,37 @@ impl SimpleElementReader<'_, '_> {
         self.0.read_element(codec, true)
     }
 
+    /// Read the element using the given codec, if the element can't be decoded, it is
+    /// skipped using its length header and an error is returned. See
+    /// `BundleElementReader::read_element_lenient`.
+    pub fn read_lenient<E: ElementCodec>(self, codec: &E) -> Result<Element<E::Element>, ElementError> {
+        self.0.read_element_lenient(codec)
+    }
+
+    /// Read the raw data of the element given its length policy, and go to the next
+    /// element if successful.
+    pub fn read_raw(self, length: ElementLength) -> Result<Element<Vec<u8>>, ReadElementError> {
+        self.0.read_element_raw(length, true)
+    }
+
+    /// Read all the remaining data of the bundle after the element's identifier. See
+    /// `BundleElementReader::read_remaining`.
+    pub fn read_remaining(self) -> Vec<u8> {
+        self.0.read_remaining()
+    }
+
+    /// Skip the element given its length policy, this is useful for unknown or unused
+    /// elements. Returns the request ID if the element was a request.
+    pub fn skip(self, length: ElementLength) -> Result<Option<u32>, ReadElementError> {
+        self.0.skip_element(length)
+    }
+
+    /// Skip the element using the length policy found in the given table. See
+    /// `BundleElementReader::skip_element_with`.
+    pub fn skip_with(self, table: &ElementLengthTable) -> Result<Option<u32>, ReadElementError> {
+        self.0.skip_element_with(table)
+    }
+
 }
 
 /// The reply variant of element, provides a way to read replies and get `Reply` elements
@@ -684,6 +916,12 @@ impl<'reader, 'bundle> ReplyElementReader<'reader, 'bundle> {
         self.0.read_element(&ReplyCodec::new(codec), true).map(Into::into)
     }
 
+    /// Read the reply element using the given codec, if the element can't be decoded,
+    /// it is skipped and an error is returned. See `BundleElementReader::read_element_lenient`.
+    pub fn read_lenient<E: ElementCodec>(self, codec: &E) -> Result<Element<E::Element>, ElementError> {
+        self.0.read_element_lenient(&ReplyCodec::new(codec)).map(Into::into)
+    }
+
 }
 
 
@@ -694,7 +932,10 @@ pub struct BundleAssembler<O = ()> {
     /// Fragments tracker.
     fragments: HashMap<(O, u32), BundleFragments>,
     /// If packets in this bundle has a prefix.
-    has_prefix: bool
+    has_prefix: bool,
+    /// Optional pool given to assembled bundles and used to release discarded
+    /// fragments.
+    pool: Option<PacketPool>,
 }
 
 impl<O> BundleAssembler<O>
@@ -705,10 +946,17 @@ where
     pub fn new(has_prefix: bool) -> Self {
         Self {
             fragments: HashMap::new(),
-            has_prefix
+            has_prefix,
+            pool: None,
         }
     }
 
+    /// Use the given pool for assembled bundles and discarded fragments.
+    pub fn with_pool(mut self, pool: PacketPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
     /// Add the given packet to internal fragments and try to make a bundle if all fragments
     /// were received. *Special case for packet with no sequence number, in such case a bundle
     /// with this single packet is returned.*
@@ -718,11 +966,11 @@ where
             match self.fragments.entry((from, seq_first)) {
                 Entry::Occupied(mut o) => {
                     if o.get().is_old() {
-                        o.get_mut().reset();
+                        o.get_mut().reset(self.pool.as_ref());
                     }
                     o.get_mut().set(seq, packet);
                     if o.get().is_full() {
-                        Some(o.remove().into_bundle(self.has_prefix))
+                        Some(pooled(self.pool.as_ref(), o.remove().into_bundle(self.has_prefix)))
                     } else {
                         None
                     }
@@ -733,18 +981,35 @@ where
                 }
             }
         } else {
-            Some(Bundle::from_single(packet, self.has_prefix))
+            Some(pooled(self.pool.as_ref(), Bundle::from_single(packet, self.has_prefix)))
         }
     }
 
     /// Clean all incomplete outdated fragments.
     pub fn cleanup(&mut self) {
-        self.fragments.retain(|_, v| !v.is_old());
+        let pool = self.pool.as_ref();
+        self.fragments.retain(|_, v| {
+            if v.is_old() {
+                v.reset(pool);
+                false
+            } else {
+                true
+            }
+        });
     }
 
 }
 
 
+/// Internal function to attach the pool, if any, to an assembled bundle.
+fn pooled(pool: Option<&PacketPool>, bundle: Bundle) -> Bundle {
+    match pool {
+        Some(pool) => bundle.with_pool(pool.clone()),
+        None => bundle
+    }
+}
+
+
 /// Internal structure to keep fragments from a given sequence.
 struct BundleFragments {
     fragments: Vec<Option<Box<Packet>>>,  // Using boxes to avoid moving huge structures.
@@ -763,9 +1028,13 @@ impl BundleFragments {
         }
     }
 
-    /// Reset all fragments.
-    fn reset(&mut self) {
-        self.fragments.iter_mut().for_each(|o| *o = None);
+    /// Reset all fragments, releasing them to the given pool if any.
+    fn reset(&mut self, pool: Option<&PacketPool>) {
+        let packets = self.fragments.iter_mut().filter_map(Option::take);
+        match pool {
+            Some(pool) => pool.release_all(packets),
+            None => packets.for_each(drop)
+        }
         self.seq_count = 0;
     }
 
@@ -799,3 +1068,192 @@ impl BundleFragments {
     }
 
 }
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::net::element::{Var16ElementCodec, Var32ElementCodec, FixedElementCodec};
+
+    /// A codec that always fails to decode, with a valid length header.
+    struct FailingCodec;
+
+    impl ElementCodec for FailingCodec {
+        const LEN: ElementLength = ElementLength::Variable16;
+        type Element = ();
+        fn encode<W: Write>(&self, _write: W, _input: Self::Element) -> io::Result<()> {
+            Ok(())
+        }
+        fn decode<R: Read + Seek>(&self, _read: R, _len: u64) -> io::Result<Self::Element> {
+            Err(io::ErrorKind::InvalidData.into())
+        }
+    }
+
+    /// Create a finalized bundle with a large non-request element spanning two
+    /// packets, followed by two requests in the second packet.
+    fn multi_packet_bundle() -> Bundle {
+        let codec = Var16ElementCodec::new();
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x10, &codec, (0..2000).map(|i| i as u8).collect());
+        bundle.add_request(0x11, &codec, vec![1, 2, 3], 7);
+        bundle.add_request(0x12, &codec, vec![4, 5], 8);
+        bundle.finalize(&mut 0);
+        assert_eq!(bundle.len(), 2);
+        bundle
+    }
+
+    #[test]
+    fn finalize_checksum() {
+
+        let mut bundle = multi_packet_bundle();
+        bundle.set_checksum(true);
+        bundle.finalize(&mut 0);
+
+        for packet in bundle.get_packets() {
+            let mut received = Packet::new_boxed(false);
+            received.get_raw_data_mut()[..packet.raw_len()].copy_from_slice(&packet.get_raw_data()[..packet.raw_len()]);
+            received.sync_state(packet.raw_len()).unwrap();
+            assert!(received.has_checksum());
+            assert_eq!(received.get_body_data(), packet.get_body_data());
+            assert_eq!(received.get_request_first_offset(), packet.get_request_first_offset());
+        }
+
+    }
+
+    #[test]
+    fn reader_across_packets() {
+
+        let bundle = multi_packet_bundle();
+        let bodies = bundle.get_packets().iter()
+            .flat_map(|packet| packet.get_body_data().iter().copied())
+            .collect::<Vec<u8>>();
+        let first_len = bundle.get_packets()[0].body_len();
+
+        let mut reader = BundleReader::new(&bundle);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, bodies);
+
+        // Reads crossing the packet boundary.
+        let mut reader = BundleReader::new(&bundle);
+        let mut buf = vec![0; first_len - 10];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.get_packet_body_pos(), first_len - 10);
+        let mut buf = [0; 20];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], bodies[first_len - 10..first_len + 10]);
+        assert_eq!(reader.get_packet_body_pos(), 10);
+        assert_eq!(reader.pos(), first_len as u64 + 10);
+
+        // Forward seek from the middle of the first packet to the second one.
+        let mut reader = BundleReader::new(&bundle);
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        reader.seek(SeekFrom::Start(first_len as u64 + 5)).unwrap();
+        assert_eq!(reader.get_packet_body_pos(), 5);
+        assert_eq!(reader.read_u8().unwrap(), bodies[first_len + 5]);
+
+        // Backward seek to the first packet.
+        reader.seek(SeekFrom::Start(3)).unwrap();
+        assert_eq!(reader.get_packet_body_pos(), 3);
+        assert_eq!(reader.read_u8().unwrap(), bodies[3]);
+
+    }
+
+    #[test]
+    fn element_reader_requests_in_later_packet() {
+
+        let bundle = multi_packet_bundle();
+        let codec = Var16ElementCodec::new();
+
+        // Skipping seeks over the packet boundary.
+        let mut reader = bundle.get_element_reader();
+        assert!(!reader.is_request());
+        assert_eq!(reader.skip_element(ElementLength::Variable16).unwrap(), None);
+        assert_eq!(reader.read_id(), Some(0x11));
+        assert!(reader.is_request());
+        let elt = reader.read_element(&codec, true).unwrap();
+        assert_eq!((elt.request_id, elt.element), (Some(7), vec![1, 2, 3]));
+        let elt = reader.read_element(&codec, true).unwrap();
+        assert_eq!((elt.request_id, elt.element), (Some(8), vec![4, 5]));
+        assert!(reader.read_id().is_none());
+
+        // Reading goes over the packet boundary.
+        let mut reader = bundle.get_element_reader();
+        let elt = reader.read_element(&codec, true).unwrap();
+        assert_eq!(elt.request_id, None);
+        assert_eq!(elt.element, (0..2000).map(|i| i as u8).collect::<Vec<u8>>());
+        let elt = reader.read_element(&codec, true).unwrap();
+        assert_eq!(elt.request_id, Some(7));
+        let elt = reader.read_element(&codec, true).unwrap();
+        assert_eq!(elt.request_id, Some(8));
+
+    }
+
+    #[test]
+    fn element_truncated_header() {
+
+        // A variable 32 element with only 2 bytes of length.
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x20, &FixedElementCodec::<2>::new(), vec![0x10, 0x00]);
+        bundle.finalize(&mut 0);
+
+        let mut reader = bundle.get_element_reader();
+        assert!(matches!(reader.read_element_raw(ElementLength::Variable32, true), Err(ReadElementError::TooShortPacket)));
+        assert!(matches!(reader.skip_element(ElementLength::Variable32), Err(ReadElementError::TooShortPacket)));
+        let Err(err) = reader.read_element_lenient(&Var32ElementCodec::new()) else { panic!() };
+        assert_eq!(err.id, 0x20);
+        assert!(matches!(err.error, ReadElementError::TooShortPacket));
+        assert!(!err.skipped);
+
+    }
+
+    #[test]
+    fn element_oversized() {
+
+        // A variable 32 element claiming 4 GiB of data.
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x20, &FixedElementCodec::<6>::new(), vec![0xFF, 0xFF, 0xFF, 0xFF, 1, 2]);
+        bundle.finalize(&mut 0);
+
+        let mut reader = bundle.get_element_reader();
+        assert!(matches!(reader.read_element_raw(ElementLength::Variable32, true), Err(ReadElementError::Io(_))));
+        assert!(matches!(reader.skip_element(ElementLength::Variable32), Err(ReadElementError::Io(_))));
+
+        let Err(err) = reader.read_element_lenient(&Var32ElementCodec::new()) else { panic!() };
+        assert_eq!(err.id, 0x20);
+        assert!(matches!(err.error, ReadElementError::Io(_)));
+        assert!(!err.skipped);
+
+        // The reader is left on the element after errors.
+        assert_eq!(reader.read_id(), Some(0x20));
+        assert_eq!(reader.read_element_raw(ElementLength::Fixed(6), true).unwrap().element, [0xFF, 0xFF, 0xFF, 0xFF, 1, 2]);
+
+    }
+
+    #[test]
+    fn element_lenient_skip() {
+
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_request(0x30, &Var16ElementCodec::new(), vec![1, 2, 3], 5);
+        bundle.add_element(0x31, &Var16ElementCodec::new(), vec![4]);
+        bundle.finalize(&mut 0);
+
+        let mut reader = bundle.get_element_reader();
+        let Err(err) = reader.read_element_lenient(&FailingCodec) else { panic!() };
+        assert_eq!(err.id, 0x30);
+        assert!(matches!(err.error, ReadElementError::Io(_)));
+        assert!(err.skipped);
+
+        let elt = reader.read_element_lenient(&Var16ElementCodec::new()).unwrap();
+        assert_eq!((elt.request_id, elt.element), (None, vec![4]));
+        assert!(reader.read_id().is_none());
+
+        let mut reader = bundle.get_element_reader();
+        assert_eq!(reader.skip_element(ElementLength::Variable16).unwrap(), Some(5));
+        assert_eq!(reader.skip_element(ElementLength::Variable16).unwrap(), None);
+        assert!(reader.read_id().is_none());
+
+    }
+
+}
@@ -0,0 +1,360 @@
+//! Capture files, recording timestamped bundles exchanged with peers in a
+//! compact format, which can be read back to replay a session or to build
+//! a corpus for elements' decoders.
+//!
+//! A capture starts with a header (magic and version), followed by records
+//! until the end of the file. Each record contains a bundle with its time
+//! since the start of the capture, direction and peer address.
+
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::{Duration, Instant};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use thiserror::Error;
+
+use super::packet::{Packet, PacketSyncError, PACKET_PREFIX_LEN, PACKET_FLAGS_LEN};
+use super::bundle::Bundle;
+
+
+/// Magic of a capture file.
+pub const CAPTURE_MAGIC: &[u8; 4] = b"WGCP";
+
+/// Current version of the capture format.
+pub const CAPTURE_VERSION: u16 = 1;
+
+
+/// Direction of a captured bundle, relative to the capturing application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptureDirection {
+    /// The bundle has been received from the peer.
+    Inbound = 0,
+    /// The bundle has been sent to the peer.
+    Outbound = 1,
+}
+
+/// A bundle read from a capture.
+pub struct CaptureRecord {
+    /// Time since the start of the capture.
+    pub time: Duration,
+    pub direction: CaptureDirection,
+    /// Address of the peer.
+    pub addr: SocketAddrV4,
+    pub bundle: Bundle,
+}
+
+
+/// A writer for capture files, bundles should be written after decryption
+/// and must be finalized.
+pub struct CaptureWriter<W> {
+    inner: W,
+    /// Start time of the capture, used to timestamp bundles.
+    start: Instant,
+}
+
+impl<W: Write> CaptureWriter<W> {
+
+    /// Create a new capture writer, the header is immediately written.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(CAPTURE_MAGIC)?;
+        writer.write_u16::<LE>(CAPTURE_VERSION)?;
+        Ok(Self {
+            inner: writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Write a bundle, timestamped with the time elapsed since the creation
+    /// of this writer.
+    pub fn write_bundle(&mut self, direction: CaptureDirection, addr: SocketAddrV4, bundle: &Bundle) -> io::Result<()> {
+        self.write_bundle_at(self.start.elapsed(), direction, addr, bundle)
+    }
+
+    /// Write a bundle with an explicit time since the start of the capture.
+    pub fn write_bundle_at(&mut self, time: Duration, direction: CaptureDirection, addr: SocketAddrV4, bundle: &Bundle) -> io::Result<()> {
+
+        let packets = bundle.get_packets();
+        let has_prefix = packets.first().map(|packet| packet.has_prefix()).unwrap_or(false);
+
+        self.inner.write_u64::<LE>(time.as_micros() as u64)?;
+        self.inner.write_u8(direction as u8)?;
+        self.inner.write_u32::<LE>(u32::from(*addr.ip()))?;
+        self.inner.write_u16::<LE>(addr.port())?;
+        self.inner.write_u8(has_prefix as u8)?;
+        self.inner.write_u16::<LE>(record_len(packets.len())?)?;
+
+        for packet in packets {
+            let raw_data = &packet.get_raw_data()[..packet.raw_len()];
+            self.inner.write_u16::<LE>(record_len(raw_data.len())?)?;
+            self.inner.write_all(raw_data)?;
+        }
+
+        Ok(())
+
+    }
+
+    #[inline]
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+}
+
+
+/// A reader for capture files, records can be read one by one or through
+/// the iterator implementation.
+pub struct CaptureReader<R> {
+    inner: R,
+}
+
+impl<R: Read> CaptureReader<R> {
+
+    /// Create a new capture reader, the header is immediately read and
+    /// checked.
+    pub fn new(mut reader: R) -> Result<Self, CaptureError> {
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != CAPTURE_MAGIC {
+            return Err(CaptureError::InvalidMagic);
+        }
+
+        let version = reader.read_u16::<LE>()?;
+        if version != CAPTURE_VERSION {
+            return Err(CaptureError::UnsupportedVersion(version));
+        }
+
+        Ok(Self { inner: reader })
+
+    }
+
+    /// Read the next record, none is returned at the end of the capture.
+    pub fn read_record(&mut self) -> Result<Option<CaptureRecord>, CaptureError> {
+
+        // Only the start of a record is allowed to be the end of the capture,
+        // a record truncated in its time field is an unexpected end.
+        let mut time = [0; 8];
+        let mut time_len = 0;
+        while time_len < time.len() {
+            match self.inner.read(&mut time[time_len..]) {
+                Ok(0) if time_len == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(len) => time_len += len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let time = Duration::from_micros(u64::from_le_bytes(time));
+
+        let direction = match self.inner.read_u8()? {
+            0 => CaptureDirection::Inbound,
+            1 => CaptureDirection::Outbound,
+            raw => return Err(CaptureError::InvalidDirection(raw)),
+        };
+
+        let ip = Ipv4Addr::from(self.inner.read_u32::<LE>()?);
+        let port = self.inner.read_u16::<LE>()?;
+        let has_prefix = self.inner.read_u8()? != 0;
+        let packets_count = self.inner.read_u16::<LE>()?;
+
+        let mut packets = Vec::with_capacity(packets_count as usize);
+        for _ in 0..packets_count {
+
+            let len = self.inner.read_u16::<LE>()? as usize;
+            let mut packet = Packet::new_boxed(has_prefix);
+
+            let raw_data = packet.get_raw_data_mut();
+            if len > raw_data.len() {
+                return Err(CaptureError::PacketTooLong(len));
+            } else if len < if has_prefix { PACKET_PREFIX_LEN } else { 0 } + PACKET_FLAGS_LEN {
+                return Err(CaptureError::PacketTooShort(len));
+            }
+
+            self.inner.read_exact(&mut raw_data[..len])?;
+            packet.sync_state(len).map_err(CaptureError::InvalidPacket)?;
+            packets.push(packet);
+
+        }
+
+        Ok(Some(CaptureRecord {
+            time,
+            direction,
+            addr: SocketAddrV4::new(ip, port),
+            bundle: Bundle::from_packets(packets, has_prefix),
+        }))
+
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+
+    type Item = Result<CaptureRecord, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+
+}
+
+
+/// Internal function to convert a count or length to be written in a record,
+/// returning an invalid input error if it doesn't fit.
+fn record_len(len: usize) -> io::Result<u16> {
+    u16::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "capture record length overflow"))
+}
+
+
+/// Errors that can happen while reading a capture.
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    /// Invalid magic signature for the capture.
+    #[error("invalid magic")]
+    InvalidMagic,
+    /// The capture has been written with an unsupported version.
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u16),
+    /// Invalid direction of a record.
+    #[error("invalid direction {0}")]
+    InvalidDirection(u8),
+    /// A packet is too long to be loaded.
+    #[error("packet too long: {0} bytes")]
+    PacketTooLong(usize),
+    /// A packet is too short to contain its prefix and flags.
+    #[error("packet too short: {0} bytes")]
+    PacketTooShort(usize),
+    /// A packet can't be synchronized from its data.
+    #[error("invalid packet: {0:?}")]
+    InvalidPacket(PacketSyncError),
+    /// IO error while reading.
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::net::bundle::BundleElement;
+    use crate::net::element::Var16ElementCodec;
+
+    const ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 20014);
+
+    #[test]
+    fn capture_round_trip() {
+
+        // The second element doesn't fit in the first packet.
+        let mut bundle = Bundle::new_empty(true);
+        bundle.add_element(0x10, &Var16ElementCodec::new(), vec![1, 2, 3]);
+        bundle.add_request(0x11, &Var16ElementCodec::new(), vec![0xAB; 2000], 42);
+        bundle.finalize(&mut 100);
+        assert_eq!(bundle.len(), 2);
+
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer.write_bundle_at(Duration::from_millis(1500), CaptureDirection::Outbound, ADDR, &bundle).unwrap();
+        writer.write_bundle_at(Duration::from_millis(2000), CaptureDirection::Inbound, ADDR, &bundle).unwrap();
+        let data = writer.into_inner();
+
+        let records = CaptureReader::new(&data[..]).unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].time, Duration::from_millis(1500));
+        assert_eq!(records[0].direction, CaptureDirection::Outbound);
+        assert_eq!(records[1].direction, CaptureDirection::Inbound);
+        assert_eq!(records[0].addr, ADDR);
+
+        let read_bundle = &records[0].bundle;
+        assert_eq!(read_bundle.len(), 2);
+        for (read, written) in read_bundle.get_packets().iter().zip(bundle.get_packets()) {
+            assert_eq!(read.get_prefix(), written.get_prefix());
+            assert_eq!(read.get_raw_data()[..read.raw_len()], written.get_raw_data()[..written.raw_len()]);
+        }
+
+        let mut reader = read_bundle.get_element_reader();
+        let Some(BundleElement::Simple(0x10, elt)) = reader.next_element() else { panic!() };
+        assert_eq!(elt.read(&Var16ElementCodec::new()).unwrap().element, [1, 2, 3]);
+        let Some(BundleElement::Simple(0x11, elt)) = reader.next_element() else { panic!() };
+        let elt = elt.read(&Var16ElementCodec::new()).unwrap();
+        assert_eq!(elt.request_id, Some(42));
+        assert_eq!(elt.element, vec![0xAB; 2000]);
+        assert!(reader.next_element().is_none());
+
+    }
+
+    /// Write a capture with a single record of one packet with the given raw data.
+    fn single_packet_capture(has_prefix: bool, raw_data: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(CAPTURE_MAGIC);
+        data.write_u16::<LE>(CAPTURE_VERSION).unwrap();
+        data.write_u64::<LE>(0).unwrap();
+        data.write_u8(0).unwrap();
+        data.write_u32::<LE>(u32::from(*ADDR.ip())).unwrap();
+        data.write_u16::<LE>(ADDR.port()).unwrap();
+        data.write_u8(has_prefix as u8).unwrap();
+        data.write_u16::<LE>(1).unwrap();
+        data.write_u16::<LE>(raw_data.len() as u16).unwrap();
+        data.extend_from_slice(raw_data);
+        data
+    }
+
+    #[test]
+    fn capture_malformed() {
+
+        let data = single_packet_capture(true, &[0, 0, 0]);
+        let mut reader = CaptureReader::new(&data[..]).unwrap();
+        assert!(matches!(reader.read_record(), Err(CaptureError::PacketTooShort(3))));
+
+        let data = single_packet_capture(false, &[0]);
+        let mut reader = CaptureReader::new(&data[..]).unwrap();
+        assert!(matches!(reader.read_record(), Err(CaptureError::PacketTooShort(1))));
+
+        // Requests flag without the room for its footer.
+        let data = single_packet_capture(false, &[0x01, 0x00]);
+        let mut reader = CaptureReader::new(&data[..]).unwrap();
+        assert!(matches!(reader.read_record(), Err(CaptureError::InvalidPacket(PacketSyncError::TooShort))));
+
+        // Truncated record.
+        let mut data = single_packet_capture(false, &[0, 0, 1, 2, 3]);
+        data.pop();
+        let mut reader = CaptureReader::new(&data[..]).unwrap();
+        assert!(matches!(reader.read_record(), Err(CaptureError::Io(_))));
+
+    }
+
+    #[test]
+    fn capture_truncated_time() {
+
+        let mut data = single_packet_capture(false, &[0, 0]);
+        let record_len = data.len();
+
+        // A record cut off in its time field is not a clean end of capture.
+        data.extend_from_slice(&[0; 8]);
+        for time_len in 1..8 {
+            let data = &data[..record_len + time_len];
+            let mut reader = CaptureReader::new(data).unwrap();
+            assert!(reader.read_record().unwrap().is_some());
+            let res = reader.read_record();
+            assert!(matches!(res, Err(CaptureError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof), "{time_len} bytes");
+        }
+
+        let mut reader = CaptureReader::new(&data[..record_len]).unwrap();
+        assert!(reader.read_record().unwrap().is_some());
+        assert!(reader.read_record().unwrap().is_none());
+
+    }
+
+}
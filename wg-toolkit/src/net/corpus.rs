@@ -0,0 +1,358 @@
+//! Statistics over a corpus of captures, giving the frequency and sizes of
+//! each element, with deduplicated example payloads. This is intended to
+//! find which elements are worth implementing next.
+//!
+//! Elements are read with the length policies of an [`ElementLengthTable`]
+//! for each direction, because elements' identifiers are not shared between
+//! directions. Elements missing from the table can't be skipped, so they
+//! stop the reading of their bundle, the rest of the bundle from their
+//! length header is then recorded as their payload, so that their layout
+//! can still be inferred.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+
+use super::bundle::{Bundle, BundleElement};
+use super::capture::{CaptureReader, CaptureDirection, CaptureError};
+use super::element::{ElementLengthTable, RawElementCodec, RawElementCodecLenVar32};
+use super::element::reply::REPLY_ID;
+
+
+/// Default maximum number of distinct example payloads kept per element.
+pub const DEFAULT_MAX_SAMPLES: usize = 8;
+
+
+/// Statistics of a single element identifier in a given direction.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ElementStats {
+    /// Number of occurrences.
+    pub count: usize,
+    /// Number of occurrences as a request.
+    pub request_count: usize,
+    /// Number of distinct payloads.
+    pub distinct_count: usize,
+    /// Total size of all payloads.
+    pub total_size: usize,
+    /// Number of occurrences for each payload size.
+    pub sizes: BTreeMap<usize, usize>,
+    /// The first distinct payloads encountered.
+    pub samples: Vec<Vec<u8>>,
+    /// Hashes of all distinct payloads.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hashes: HashSet<u64>,
+}
+
+impl ElementStats {
+
+    /// Smallest payload size.
+    pub fn min_size(&self) -> Option<usize> {
+        self.sizes.keys().next().copied()
+    }
+
+    /// Largest payload size.
+    pub fn max_size(&self) -> Option<usize> {
+        self.sizes.keys().next_back().copied()
+    }
+
+    /// Average payload size.
+    pub fn average_size(&self) -> Option<f64> {
+        (self.count != 0).then(|| self.total_size as f64 / self.count as f64)
+    }
+
+}
+
+
+/// Statistics accumulated over captures or bundles.
+#[derive(Debug, Clone)]
+pub struct CorpusStats {
+    inbound_table: ElementLengthTable,
+    outbound_table: ElementLengthTable,
+    max_samples: usize,
+    /// Number of bundles read.
+    bundle_count: usize,
+    /// Statistics of read elements, replies are identified by `REPLY_ID`.
+    elements: BTreeMap<(CaptureDirection, u8), ElementStats>,
+    /// Statistics of elements missing from the tables, their payloads are the
+    /// rest of the bundles they stopped.
+    unknown: BTreeMap<(CaptureDirection, u8), ElementStats>,
+    /// Number of bundles stopped by invalid elements.
+    error_count: usize,
+}
+
+impl CorpusStats {
+
+    /// Create new statistics, reading elements with the given tables for
+    /// elements received from peers (inbound) and sent to them (outbound).
+    pub fn new(inbound_table: ElementLengthTable, outbound_table: ElementLengthTable) -> Self {
+        Self {
+            inbound_table,
+            outbound_table,
+            max_samples: DEFAULT_MAX_SAMPLES,
+            bundle_count: 0,
+            elements: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+            error_count: 0,
+        }
+    }
+
+    /// Set the maximum number of distinct example payloads kept per element.
+    pub fn set_max_samples(&mut self, max_samples: usize) {
+        self.max_samples = max_samples;
+    }
+
+    /// Add all bundles of a capture.
+    pub fn add_capture<R: Read>(&mut self, reader: CaptureReader<R>) -> Result<(), CaptureError> {
+        for record in reader {
+            let record = record?;
+            self.add_bundle(record.direction, &record.bundle);
+        }
+        Ok(())
+    }
+
+    /// Add all elements of a bundle.
+    pub fn add_bundle(&mut self, direction: CaptureDirection, bundle: &Bundle) {
+
+        self.bundle_count += 1;
+
+        let table = match direction {
+            CaptureDirection::Inbound => &self.inbound_table,
+            CaptureDirection::Outbound => &self.outbound_table,
+        };
+
+        let mut reader = bundle.get_element_reader();
+        loop {
+
+            let request = reader.is_request();
+            let Some(elt) = reader.next_element() else {
+                break;
+            };
+
+            let (id, res) = match elt {
+                BundleElement::Simple(id, elt_reader) => {
+                    let Some(length) = table.get_length(id) else {
+                        let stats = self.unknown.entry((direction, id)).or_default();
+                        add_payload(stats, elt_reader.read_remaining(), request, self.max_samples);
+                        break;
+                    };
+                    (id, elt_reader.read_raw(length))
+                }
+                BundleElement::Reply(_, elt_reader) => {
+                    let codec = RawElementCodec::<RawElementCodecLenVar32>::new();
+                    (REPLY_ID, elt_reader.read(&codec))
+                }
+            };
+
+            match res {
+                Ok(elt) => {
+                    let stats = self.elements.entry((direction, id)).or_default();
+                    add_payload(stats, elt.element, elt.request_id.is_some(), self.max_samples);
+                }
+                Err(_) => {
+                    self.error_count += 1;
+                    break;
+                }
+            }
+
+        }
+
+    }
+
+    /// Number of bundles read.
+    #[inline]
+    pub fn bundle_count(&self) -> usize {
+        self.bundle_count
+    }
+
+    /// Number of bundles stopped by invalid elements.
+    #[inline]
+    pub fn error_count(&self) -> usize {
+        self.error_count
+    }
+
+    /// Get the statistics of an element.
+    pub fn get(&self, direction: CaptureDirection, id: u8) -> Option<&ElementStats> {
+        self.elements.get(&(direction, id))
+    }
+
+    /// Iterate over the statistics of all read elements, ordered by direction
+    /// and identifier.
+    pub fn elements(&self) -> impl Iterator<Item = (CaptureDirection, u8, &'_ ElementStats)> + '_ {
+        self.elements.iter().map(|(&(direction, id), stats)| (direction, id, stats))
+    }
+
+    /// Get the statistics of an element missing from the tables, its count is
+    /// the number of bundles it stopped and its payloads are the rest of these
+    /// bundles, from its length header.
+    pub fn get_unknown(&self, direction: CaptureDirection, id: u8) -> Option<&ElementStats> {
+        self.unknown.get(&(direction, id))
+    }
+
+    /// Iterate over the statistics of all elements missing from the tables,
+    /// ordered by direction and identifier. See [`Self::get_unknown`].
+    pub fn unknown_elements(&self) -> impl Iterator<Item = (CaptureDirection, u8, &'_ ElementStats)> + '_ {
+        self.unknown.iter().map(|(&(direction, id), stats)| (direction, id, stats))
+    }
+
+}
+
+
+/// Internal function to add a payload to element's statistics.
+fn add_payload(stats: &mut ElementStats, payload: Vec<u8>, request: bool, max_samples: usize) {
+
+    stats.count += 1;
+    stats.total_size += payload.len();
+    *stats.sizes.entry(payload.len()).or_default() += 1;
+
+    if request {
+        stats.request_count += 1;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    payload.hash(&mut hasher);
+    if stats.hashes.insert(hasher.finish()) {
+        stats.distinct_count += 1;
+        if stats.samples.len() < max_samples {
+            stats.samples.push(payload);
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::net::element::{ElementLength, FixedElementCodec, Var16ElementCodec};
+    use crate::net::infer::{infer_layout, InferredField};
+
+    fn inbound_table() -> ElementLengthTable {
+        let mut table = ElementLengthTable::new();
+        table.insert(0x10, "Fixed", ElementLength::Fixed(3));
+        table.insert(0x11, "Variable", ElementLength::Variable16);
+        table
+    }
+
+    #[test]
+    fn corpus_stats() {
+
+        let fixed = FixedElementCodec::<3>::new();
+        let var = Var16ElementCodec::new();
+
+        let mut stats = CorpusStats::new(inbound_table(), ElementLengthTable::new());
+        stats.set_max_samples(1);
+
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x10, &fixed, vec![1, 2, 3]);
+        bundle.add_request(0x11, &var, vec![4, 5], 1);
+        bundle.add_element(0x10, &fixed, vec![1, 2, 3]);
+        bundle.finalize(&mut 0);
+        stats.add_bundle(CaptureDirection::Inbound, &bundle);
+
+        // The unknown element stops the bundle, the last element is not read.
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_element(0x10, &fixed, vec![7, 8, 9]);
+        bundle.add_element(0x20, &var, vec![0]);
+        bundle.add_element(0x10, &fixed, vec![0, 0, 0]);
+        bundle.finalize(&mut 0);
+        stats.add_bundle(CaptureDirection::Inbound, &bundle);
+
+        // Elements are not shared between directions.
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_reply(&var, vec![9, 9, 9, 9], 1);
+        bundle.add_element(0x10, &fixed, vec![1, 2, 3]);
+        bundle.finalize(&mut 0);
+        stats.add_bundle(CaptureDirection::Outbound, &bundle);
+
+        assert_eq!(stats.bundle_count(), 3);
+        assert_eq!(stats.error_count(), 0);
+
+        let fixed_stats = stats.get(CaptureDirection::Inbound, 0x10).unwrap();
+        assert_eq!(fixed_stats.count, 3);
+        assert_eq!(fixed_stats.request_count, 0);
+        assert_eq!(fixed_stats.distinct_count, 2);
+        assert_eq!(fixed_stats.samples, [vec![1, 2, 3]]);
+        assert_eq!(fixed_stats.average_size(), Some(3.0));
+
+        let var_stats = stats.get(CaptureDirection::Inbound, 0x11).unwrap();
+        assert_eq!(var_stats.count, 1);
+        assert_eq!(var_stats.request_count, 1);
+        assert_eq!(var_stats.samples, [vec![4, 5]]);
+
+        let reply_stats = stats.get(CaptureDirection::Outbound, REPLY_ID).unwrap();
+        assert_eq!(reply_stats.count, 1);
+        assert_eq!(reply_stats.samples, [vec![9, 9, 9, 9]]);
+
+        assert!(stats.get(CaptureDirection::Outbound, 0x10).is_none());
+        assert_eq!(stats.unknown_elements().map(|(direction, id, stats)| (direction, id, stats.count)).collect::<Vec<_>>(), [
+            (CaptureDirection::Inbound, 0x20, 1),
+            (CaptureDirection::Outbound, 0x10, 1),
+        ]);
+
+        // Unknown elements keep the rest of the bundle, from their length header.
+        let unknown_stats = stats.get_unknown(CaptureDirection::Inbound, 0x20).unwrap();
+        assert_eq!(unknown_stats.samples, [vec![1, 0, 0, 0x10, 0, 0, 0]]);
+        let unknown_stats = stats.get_unknown(CaptureDirection::Outbound, 0x10).unwrap();
+        assert_eq!(unknown_stats.samples, [vec![1, 2, 3]]);
+
+    }
+
+    #[test]
+    fn corpus_stats_sizes() {
+
+        let var = Var16ElementCodec::new();
+        let mut stats = CorpusStats::new(inbound_table(), ElementLengthTable::new());
+
+        let mut bundle = Bundle::new_empty(false);
+        for payload in [vec![1], vec![1, 2, 3], vec![4, 5, 6], vec![1, 2, 3, 4, 5]] {
+            bundle.add_element(0x11, &var, payload);
+        }
+        bundle.finalize(&mut 0);
+        stats.add_bundle(CaptureDirection::Inbound, &bundle);
+
+        let var_stats = stats.get(CaptureDirection::Inbound, 0x11).unwrap();
+        assert_eq!(var_stats.count, 4);
+        assert_eq!(var_stats.distinct_count, 4);
+        assert_eq!(var_stats.samples.len(), 4);
+        assert_eq!(var_stats.total_size, 12);
+        assert_eq!(var_stats.sizes, BTreeMap::from([(1, 1), (3, 2), (5, 1)]));
+        assert_eq!(var_stats.min_size(), Some(1));
+        assert_eq!(var_stats.max_size(), Some(5));
+        assert_eq!(var_stats.average_size(), Some(3.0));
+
+    }
+
+    #[test]
+    fn corpus_stats_unknown() {
+
+        let var = Var16ElementCodec::new();
+        let mut stats = CorpusStats::new(ElementLengthTable::new(), ElementLengthTable::new());
+        stats.set_max_samples(2);
+
+        for payload in [vec![1, 2], vec![3, 4], vec![1, 2], vec![5, 6]] {
+            let mut bundle = Bundle::new_empty(false);
+            bundle.add_request(0x20, &var, payload, 7);
+            bundle.finalize(&mut 0);
+            stats.add_bundle(CaptureDirection::Inbound, &bundle);
+        }
+
+        // Samples are deduplicated and bounded.
+        let unknown_stats = stats.get_unknown(CaptureDirection::Inbound, 0x20).unwrap();
+        assert_eq!(unknown_stats.count, 4);
+        assert_eq!(unknown_stats.request_count, 4);
+        assert_eq!(unknown_stats.distinct_count, 3);
+        assert_eq!(unknown_stats.samples, [
+            vec![2, 0, 7, 0, 0, 0, 0, 0, 1, 2],
+            vec![2, 0, 7, 0, 0, 0, 0, 0, 3, 4],
+        ]);
+
+        // The length header is found by the inference.
+        let fields = infer_layout(&unknown_stats.samples);
+        assert_eq!(fields[0], InferredField::Int { width: 2, constant: Some(2) });
+
+    }
+
+}
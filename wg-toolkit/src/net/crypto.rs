@@ -0,0 +1,69 @@
+//! Cryptographic helpers for setting up login servers, such as generating
+//! the RSA key pair used to encrypt login requests.
+
+use rand::rngs::OsRng;
+use rand::{RngCore, CryptoRng};
+
+use rsa::pkcs8::{ToPrivateKey, ToPublicKey, LineEnding};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use thiserror::Error;
+
+
+/// Default size of login keys, in bits, as used by the official servers.
+pub const DEFAULT_LOGIN_KEY_BITS: usize = 2048;
+
+
+/// A RSA key pair used by a login app, the public key must be distributed
+/// to clients and the private key is used to decrypt login requests.
+pub struct LoginKeyPair {
+    pub private_key: RsaPrivateKey,
+    pub public_key: RsaPublicKey,
+}
+
+impl LoginKeyPair {
+
+    /// Export the private key as a PKCS#8 PEM document, it can be read back
+    /// with [`rsa::pkcs8::FromPrivateKey::from_pkcs8_pem`].
+    pub fn private_key_pem(&self) -> Result<String, KeyError> {
+        Ok(self.private_key.to_pkcs8_pem_with_le(LineEnding::LF)?.to_string())
+    }
+
+    /// Export the public key as a PEM public key document (X.509 subject
+    /// public key info), this is the format expected by the client in its
+    /// `loginapp_public_key` file. It can be read back with
+    /// [`rsa::pkcs8::FromPublicKey::from_public_key_pem`].
+    pub fn public_key_pem(&self) -> Result<String, KeyError> {
+        Ok(self.public_key.to_public_key_pem_with_le(LineEnding::LF)?)
+    }
+
+}
+
+
+/// Generate a new login key pair of the given size in bits, using the OS
+/// random generator.
+pub fn generate_login_keypair(bits: usize) -> Result<LoginKeyPair, KeyError> {
+    generate_login_keypair_with_rng(&mut OsRng, bits)
+}
+
+/// Generate a new login key pair of the given size in bits, using the given
+/// random generator.
+pub fn generate_login_keypair_with_rng<G>(rng: &mut G, bits: usize) -> Result<LoginKeyPair, KeyError>
+where
+    G: RngCore + CryptoRng,
+{
+    let private_key = RsaPrivateKey::new(rng, bits)?;
+    let public_key = RsaPublicKey::from(&private_key);
+    Ok(LoginKeyPair { private_key, public_key })
+}
+
+
+/// Errors that can happen while generating or exporting keys.
+#[derive(Debug, Error)]
+pub enum KeyError {
+    /// The key can't be generated.
+    #[error("rsa error: {0}")]
+    Rsa(#[from] rsa::errors::Error),
+    /// The key can't be encoded.
+    #[error("pkcs8 error: {0}")]
+    Pkcs8(#[from] rsa::pkcs8::Error),
+}
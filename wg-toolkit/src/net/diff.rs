@@ -0,0 +1,364 @@
+//! Comparison of two recorded sessions, such as a session with the retail
+//! server and one with an emulator, to find the first divergent element.
+//!
+//! Elements are compared independently for each direction, because the
+//! interleaving of inbound and outbound traffic depends on timings. Request
+//! IDs are not compared directly, because they are only counters, instead
+//! requests are numbered in order of appearance in their direction and
+//! replies are compared by the number of the request they answer. Each peer
+//! has its own counter, so request IDs are only unique in a direction.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use super::bundle::{BundleElement, SimpleElementReader, Element, ReadElementError};
+use super::capture::{CaptureReader, CaptureDirection, CaptureError};
+use super::element::{RawElementCodec, RawElementCodecLenVar32};
+use super::element::reply::REPLY_ID;
+
+
+/// An element of a session with its raw data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionElement {
+    /// Time of the bundle containing this element, since the start of the
+    /// session.
+    pub time: Duration,
+    pub direction: CaptureDirection,
+    /// Identifier of the element.
+    pub id: u8,
+    /// The request ID if the element is a request.
+    pub request_id: Option<u32>,
+    /// The request ID answered if the element is a reply.
+    pub reply_id: Option<u32>,
+    /// Raw data of the element, without its header.
+    pub data: Vec<u8>,
+}
+
+/// The first divergence found between two sessions.
+#[derive(Debug, Clone)]
+pub struct SessionDivergence<'a> {
+    /// Direction of the diverging elements.
+    pub direction: CaptureDirection,
+    /// Index of the diverging elements among the elements of this direction.
+    pub index: usize,
+    /// The diverging element of the left session, none if it has less
+    /// elements in this direction.
+    pub left: Option<&'a SessionElement>,
+    /// The diverging element of the right session, none if it has less
+    /// elements in this direction.
+    pub right: Option<&'a SessionElement>,
+    pub kind: DivergenceKind,
+}
+
+/// Kind of divergence between two elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// One of the sessions has no more elements in this direction.
+    Missing,
+    /// The elements have different identifiers.
+    Id,
+    /// Only one of the elements is a request.
+    Request,
+    /// The elements are replies to different requests, or only one of them
+    /// is a reply.
+    Reply,
+    /// The elements' data differ, starting at the given byte offset.
+    Data {
+        offset: usize,
+    },
+}
+
+
+/// Read all elements of a capture, the given function is called to read
+/// each non-reply element, it should use a raw codec with the length of
+/// this element (see [`RawElementCodec`]). Replies are read with a raw codec.
+pub fn read_session<R, F>(reader: CaptureReader<R>, mut read_element: F) -> Result<Vec<SessionElement>, DiffError>
+where
+    R: Read,
+    F: FnMut(u8, SimpleElementReader<'_, '_>) -> Result<Element<Vec<u8>>, ReadElementError>,
+{
+
+    let mut elements = Vec::new();
+
+    for record in reader {
+
+        let record = record?;
+
+        record.bundle.get_element_reader().try_for_each_element(|elt| {
+
+            let (id, reply_id, elt) = match elt {
+                BundleElement::Simple(id, reader) => {
+                    (id, None, read_element(id, reader)?)
+                }
+                BundleElement::Reply(reply_id, reader) => {
+                    let codec = RawElementCodec::<RawElementCodecLenVar32>::new();
+                    (REPLY_ID, Some(reply_id), reader.read(&codec)?)
+                }
+            };
+
+            elements.push(SessionElement {
+                time: record.time,
+                direction: record.direction,
+                id,
+                request_id: elt.request_id,
+                reply_id,
+                data: elt.element,
+            });
+
+            Ok::<_, ReadElementError>(())
+
+        }).map_err(DiffError::Element)?;
+
+    }
+
+    Ok(elements)
+
+}
+
+/// Find the first divergence between two sessions, if the sessions diverge
+/// in both directions, the divergence happening first in the left session
+/// is returned.
+pub fn diff_sessions<'a>(left: &'a [SessionElement], right: &'a [SessionElement]) -> Option<SessionDivergence<'a>> {
+    [CaptureDirection::Outbound, CaptureDirection::Inbound]
+        .into_iter()
+        .filter_map(|direction| diff_direction(left, right, direction))
+        .min_by_key(|divergence| divergence.left.map(|elt| elt.time).unwrap_or(Duration::MAX))
+}
+
+/// Internal function to find the first divergence in a single direction.
+fn diff_direction<'a>(left: &'a [SessionElement], right: &'a [SessionElement], direction: CaptureDirection) -> Option<SessionDivergence<'a>> {
+
+    // Requests are numbered for both directions, because replies go in the
+    // opposite direction of their request.
+    let left_requests = number_requests(left);
+    let right_requests = number_requests(right);
+    let request_direction = match direction {
+        CaptureDirection::Inbound => CaptureDirection::Outbound,
+        CaptureDirection::Outbound => CaptureDirection::Inbound,
+    };
+
+    let mut left_iter = left.iter().filter(|elt| elt.direction == direction);
+    let mut right_iter = right.iter().filter(|elt| elt.direction == direction);
+    let mut index = 0;
+
+    loop {
+
+        let (left_elt, right_elt) = (left_iter.next(), right_iter.next());
+
+        let kind = match (left_elt, right_elt) {
+            (None, None) => return None,
+            (Some(l), Some(r)) => {
+                let l_reply = l.reply_id.map(|id| left_requests.get(&(request_direction, id)));
+                let r_reply = r.reply_id.map(|id| right_requests.get(&(request_direction, id)));
+                if l.id != r.id {
+                    Some(DivergenceKind::Id)
+                } else if l.request_id.is_some() != r.request_id.is_some() {
+                    Some(DivergenceKind::Request)
+                } else if l_reply != r_reply {
+                    Some(DivergenceKind::Reply)
+                } else {
+                    diff_data(&l.data, &r.data).map(|offset| DivergenceKind::Data { offset })
+                }
+            }
+            _ => Some(DivergenceKind::Missing),
+        };
+
+        if let Some(kind) = kind {
+            return Some(SessionDivergence {
+                direction,
+                index,
+                left: left_elt,
+                right: right_elt,
+                kind,
+            });
+        }
+
+        index += 1;
+
+    }
+
+}
+
+/// Internal function to map request IDs of each direction to their order of
+/// appearance in this direction.
+fn number_requests(elements: &[SessionElement]) -> HashMap<(CaptureDirection, u32), usize> {
+    let mut counts = HashMap::new();
+    elements.iter()
+        .filter_map(|elt| Some((elt.direction, elt.request_id?)))
+        .map(|(direction, request_id)| {
+            let count = counts.entry(direction).or_insert(0);
+            *count += 1;
+            ((direction, request_id), *count - 1)
+        })
+        .collect()
+}
+
+/// Internal function to get the offset of the first different byte.
+fn diff_data(left: &[u8], right: &[u8]) -> Option<usize> {
+    match left.iter().zip(right).position(|(l, r)| l != r) {
+        Some(offset) => Some(offset),
+        None if left.len() != right.len() => Some(left.len().min(right.len())),
+        None => None,
+    }
+}
+
+
+/// Errors that can happen while reading a session.
+#[derive(Debug, Error)]
+pub enum DiffError {
+    /// Error while reading the capture.
+    #[error("capture error: {0}")]
+    Capture(#[from] CaptureError),
+    /// Error while reading an element.
+    #[error("element error: {0:?}")]
+    Element(ReadElementError),
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    use super::*;
+    use crate::net::bundle::Bundle;
+    use crate::net::capture::CaptureWriter;
+    use crate::net::element::Var16ElementCodec;
+
+    const ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 20014);
+
+    /// Record a session where the client sends two requests and the server
+    /// replies to the first one and sends its own request, request IDs are
+    /// taken from the given counters of the client and server.
+    fn session(client_id: u32, server_id: u32) -> Vec<SessionElement> {
+
+        let codec = Var16ElementCodec::new();
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_request(0x10, &codec, vec![1, 2, 3], client_id);
+        bundle.add_request(0x11, &codec, vec![4], client_id + 1);
+        bundle.finalize(&mut 0);
+        writer.write_bundle_at(Duration::ZERO, CaptureDirection::Outbound, ADDR, &bundle).unwrap();
+
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_reply(&codec, vec![9, 9], client_id);
+        bundle.add_request(0x20, &codec, vec![7], server_id);
+        bundle.finalize(&mut 0);
+        writer.write_bundle_at(Duration::from_millis(100), CaptureDirection::Inbound, ADDR, &bundle).unwrap();
+
+        let mut bundle = Bundle::new_empty(false);
+        bundle.add_reply(&codec, vec![5], server_id);
+        bundle.add_element(0x12, &codec, vec![6, 6]);
+        bundle.finalize(&mut 0);
+        writer.write_bundle_at(Duration::from_millis(200), CaptureDirection::Outbound, ADDR, &bundle).unwrap();
+
+        let data = writer.into_inner();
+        read_session(CaptureReader::new(&data[..]).unwrap(), |_, reader| reader.read(&codec)).unwrap()
+
+    }
+
+    #[test]
+    fn read() {
+
+        let elements = session(1, 1);
+        assert_eq!(elements.len(), 6);
+
+        assert_eq!(elements[0], SessionElement {
+            time: Duration::ZERO,
+            direction: CaptureDirection::Outbound,
+            id: 0x10,
+            request_id: Some(1),
+            reply_id: None,
+            data: vec![1, 2, 3],
+        });
+
+        assert_eq!(elements[2], SessionElement {
+            time: Duration::from_millis(100),
+            direction: CaptureDirection::Inbound,
+            id: REPLY_ID,
+            request_id: None,
+            reply_id: Some(1),
+            data: vec![9, 9],
+        });
+
+        assert_eq!(elements[5].id, 0x12);
+        assert_eq!(elements[5].request_id, None);
+
+    }
+
+    #[test]
+    fn diff_request_ids() {
+        // Both peers' counters collide in the left session.
+        assert!(diff_sessions(&session(1, 1), &session(7, 3)).is_none());
+        assert!(diff_sessions(&session(7, 3), &session(1, 1)).is_none());
+    }
+
+    #[test]
+    fn diff_id() {
+        let left = session(1, 1);
+        let mut right = session(1, 1);
+        right[0].id = 0x13;
+        let divergence = diff_sessions(&left, &right).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::Id);
+        assert_eq!(divergence.direction, CaptureDirection::Outbound);
+        assert_eq!(divergence.index, 0);
+    }
+
+    #[test]
+    fn diff_request() {
+        let left = session(1, 1);
+        let mut right = session(1, 1);
+        right[1].request_id = None;
+        let divergence = diff_sessions(&left, &right).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::Request);
+        assert_eq!(divergence.direction, CaptureDirection::Outbound);
+        assert_eq!(divergence.index, 1);
+    }
+
+    #[test]
+    fn diff_reply() {
+        let left = session(1, 1);
+        let mut right = session(1, 1);
+        right[2].reply_id = Some(2);
+        let divergence = diff_sessions(&left, &right).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::Reply);
+        assert_eq!(divergence.direction, CaptureDirection::Inbound);
+        assert_eq!(divergence.index, 0);
+    }
+
+    #[test]
+    fn diff_data() {
+
+        let left = session(1, 1);
+        let mut right = session(1, 1);
+
+        right[0].data = vec![1, 2, 4];
+        let divergence = diff_sessions(&left, &right).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::Data { offset: 2 });
+        assert_eq!(divergence.index, 0);
+
+        right[0].data = vec![1, 2];
+        let divergence = diff_sessions(&left, &right).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::Data { offset: 2 });
+
+    }
+
+    #[test]
+    fn diff_missing() {
+        let left = session(1, 1);
+        let mut right = session(1, 1);
+        right.pop();
+        let divergence = diff_sessions(&left, &right).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::Missing);
+        assert_eq!(divergence.direction, CaptureDirection::Outbound);
+        assert_eq!(divergence.index, 3);
+        assert_eq!(divergence.left.unwrap().id, 0x12);
+        assert!(divergence.right.is_none());
+    }
+
+}
@@ -0,0 +1,479 @@
+//! Generator of Lua dissectors for Wireshark, describing the packets' flags,
+//! footers and the layout of known elements. The packet layout is generated
+//! from the same constants used by packets, so it can't drift from them.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+
+use super::element::reply::REPLY_ID;
+use super::element::{ElementCodec, ElementLength, ElementLengthTable, ElementDescriptor};
+use super::packet::PACKET_PREFIX_LEN;
+use super::PacketFlags;
+
+
+/// A Lua dissector generator, elements must be registered before writing
+/// the dissector, elements with unknown identifiers are shown as raw data
+/// and stop the dissection of the packet's body.
+#[derive(Debug, Clone)]
+pub struct LuaDissector {
+    /// Name of the protocol, used as prefix for all fields.
+    name: String,
+    /// Description of the protocol.
+    description: String,
+    /// UDP ports to register the dissector on.
+    ports: Vec<u16>,
+    /// True if packets have a 4-bytes prefix.
+    has_prefix: bool,
+    /// All known elements.
+    elements: ElementLengthTable,
+}
+
+impl LuaDissector {
+
+    /// Create a new dissector for the given protocol name, which must be a
+    /// valid lowercase identifier such as `wgtk_login`.
+    ///
+    /// # Panics
+    ///
+    /// If the name is not a valid lowercase identifier, because it is used
+    /// as is in the generated Lua code.
+    pub fn new<N: Into<String>, D: Into<String>>(name: N, description: D) -> Self {
+        let name = name.into();
+        assert!(is_lowercase_ident(&name), "invalid protocol name: {name:?}");
+        Self {
+            name,
+            description: description.into(),
+            ports: Vec::new(),
+            has_prefix: false,
+            elements: ElementLengthTable::new(),
+        }
+    }
+
+    /// Create a dissector for the login app, with its default port and
+    /// all its elements.
+    pub fn new_login() -> Self {
+        let mut dissector = Self::new("wgtk_login", "BigWorld Login App");
+        dissector.add_port(20014);
+        dissector.set_elements(ElementLengthTable::new_login());
+        dissector
+    }
+
+    /// Register an UDP port on which the dissector is used.
+    pub fn add_port(&mut self, port: u16) {
+        self.ports.push(port);
+    }
+
+    /// Set to true if packets have a 4-bytes prefix.
+    pub fn set_prefix(&mut self, has_prefix: bool) {
+        self.has_prefix = has_prefix;
+    }
+
+    /// Register an element with the length of the given codec.
+    #[inline]
+    pub fn add_element<E: ElementCodec>(&mut self, id: u8, name: &str) {
+        self.elements.insert_codec::<E>(id, name);
+    }
+
+    /// Register an element with an explicit length, an element already
+    /// registered with the same identifier is replaced.
+    pub fn add_element_raw(&mut self, id: u8, name: &str, length: ElementLength) {
+        self.elements.insert(id, name, length);
+    }
+
+    /// Replace all registered elements with the given table.
+    pub fn set_elements(&mut self, elements: ElementLengthTable) {
+        self.elements = elements;
+    }
+
+    /// Iterate over all registered elements, ordered by identifier.
+    pub fn elements(&self) -> impl Iterator<Item = (u8, &'_ ElementDescriptor)> + '_ {
+        self.elements.iter().filter(|&(id, _)| id != REPLY_ID)
+    }
+
+    /// Write the Lua dissector, reply elements are always known.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+
+        let name = &self.name;
+
+        writeln!(writer, "-- Wireshark dissector for {}, generated by wg-toolkit, do not edit.", self.description.replace(['\r', '\n'], " "))?;
+        writeln!(writer)?;
+        writeln!(writer, "local proto = Proto({}, {})", LuaString(name), LuaString(&self.description))?;
+        writeln!(writer)?;
+
+        writeln!(writer, "local HAS_PREFIX = {}", self.has_prefix)?;
+        writeln!(writer, "local PREFIX_LEN = {PACKET_PREFIX_LEN}")?;
+        writeln!(writer, "local REPLY_ID = 0x{REPLY_ID:02X}")?;
+        for (flag, flag_name) in PacketFlags::ALL {
+            writeln!(writer, "local FLAG_{flag_name} = 0x{:04X}", flag.bits())?;
+        }
+        writeln!(writer)?;
+
+        // Element names and layouts, replies are builtin.
+        writeln!(writer, "local element_names = {{")?;
+        for (id, elt) in self.elements() {
+            writeln!(writer, "    [0x{id:02X}] = {},", LuaString(&elt.name))?;
+        }
+        writeln!(writer, "    [0x{REPLY_ID:02X}] = \"Reply\",")?;
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+        writeln!(writer, "local element_layouts = {{")?;
+        for (id, elt) in self.elements() {
+            writeln!(writer, "    [0x{id:02X}] = {},", lua_layout(elt.length))?;
+        }
+        writeln!(writer, "    [0x{REPLY_ID:02X}] = {},", lua_layout(ElementLength::Variable32))?;
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+
+        // Fields, flags' fields are named after their constant.
+        let flags = PacketFlags::ALL.map(|(flag, flag_name)| {
+            (flag_name.to_lowercase(), flag_description(flag_name), flag)
+        });
+
+        let mut fields = vec![
+            ("prefix", format!("ProtoField.uint32(\"{name}.prefix\", \"Prefix\", base.HEX)")),
+            ("flags", format!("ProtoField.uint16(\"{name}.flags\", \"Flags\", base.HEX)")),
+        ];
+        for (flag_name, flag_desc, flag) in &flags {
+            fields.push((flag_name, format!("ProtoField.bool(\"{name}.flags.{flag_name}\", \"{flag_desc}\", 16, nil, 0x{:04X})", flag.bits())));
+        }
+        fields.extend([
+            ("body", format!("ProtoField.bytes(\"{name}.body\", \"Body\")")),
+            ("seq_first", format!("ProtoField.uint32(\"{name}.seq_first\", \"First sequence number\")")),
+            ("seq_last", format!("ProtoField.uint32(\"{name}.seq_last\", \"Last sequence number\")")),
+            ("request_first_offset", format!("ProtoField.uint16(\"{name}.request_first_offset\", \"First request offset\")")),
+            ("seq", format!("ProtoField.uint32(\"{name}.seq\", \"Sequence number\")")),
+            ("checksum", format!("ProtoField.uint32(\"{name}.checksum\", \"Checksum\", base.HEX)")),
+            ("element_id", format!("ProtoField.uint8(\"{name}.element.id\", \"Identifier\", base.HEX, element_names)")),
+            ("element_length", format!("ProtoField.uint32(\"{name}.element.length\", \"Length\")")),
+            ("element_request_id", format!("ProtoField.uint32(\"{name}.element.request_id\", \"Request ID\")")),
+            ("element_next_request_offset", format!("ProtoField.uint16(\"{name}.element.next_request_offset\", \"Next request offset\")")),
+            ("element_reply_id", format!("ProtoField.uint32(\"{name}.element.reply_id\", \"Reply to request ID\")")),
+            ("element_data", format!("ProtoField.bytes(\"{name}.element.data\", \"Data\")")),
+            ("unknown", format!("ProtoField.bytes(\"{name}.unknown\", \"Unknown data\")")),
+        ]);
+
+        writeln!(writer, "local f = {{")?;
+        for (field_name, field_def) in &fields {
+            writeln!(writer, "    {field_name} = {field_def},")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+        writeln!(writer, "proto.fields = {{")?;
+        for (field_name, _) in &fields {
+            writeln!(writer, "    f.{field_name},")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+        writeln!(writer, "local flags_fields = {{")?;
+        for (flag_name, _, _) in &flags {
+            writeln!(writer, "    f.{flag_name},")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+
+        writer.write_all(LUA_DISSECT.as_bytes())?;
+        writeln!(writer)?;
+
+        writeln!(writer, "local udp_port = DissectorTable.get(\"udp.port\")")?;
+        for port in &self.ports {
+            writeln!(writer, "udp_port:add({port}, proto)")?;
+        }
+
+        Ok(())
+
+    }
+
+}
+
+
+/// Internal function to check that a name is a lowercase identifier.
+fn is_lowercase_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some('a'..='z' | '_'))
+        && chars.all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_'))
+}
+
+/// Internal function to get the description of a flag from its constant's
+/// name, for example `Has requests` for `HAS_REQUESTS`.
+fn flag_description(flag_name: &str) -> String {
+    let desc = flag_name.to_lowercase().replace('_', " ");
+    let mut chars = desc.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => desc,
+    }
+}
+
+/// Internal function to get the Lua layout table of an element length.
+fn lua_layout(length: ElementLength) -> String {
+    match length {
+        ElementLength::Fixed(len) => format!("{{ fixed = {len} }}"),
+        length => format!("{{ variable = {} }}", length.len()),
+    }
+}
+
+
+/// Internal wrapper to display a string as a quoted Lua string literal. Bytes
+/// that are not printable ASCII are written as decimal escapes, which are
+/// the only escapes of arbitrary bytes supported since Lua 5.1.
+struct LuaString<'a>(&'a str);
+
+impl Display for LuaString<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        for &b in self.0.as_bytes() {
+            match b {
+                b'\\' => f.write_str("\\\\")?,
+                b'"' => f.write_str("\\\"")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\r' => f.write_str("\\r")?,
+                b'\t' => f.write_str("\\t")?,
+                0x20..=0x7E => write!(f, "{}", b as char)?,
+                // Always 3 digits, so that a following digit is not part of the escape.
+                _ => write!(f, "\\{b:03}")?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+
+/// Static part of the dissector, using tables and fields defined before.
+const LUA_DISSECT: &str = r#"local function has_flag(flags, flag)
+    return bit.band(flags, flag) ~= 0
+end
+
+local function dissect_elements(buffer, tree, data, pos, footer, request_offset)
+    while pos < footer do
+
+        local id = buffer(pos, 1):uint()
+        local layout = element_layouts[id]
+        if layout == nil then
+            tree:add(f.unknown, buffer(pos, footer - pos))
+            return
+        end
+
+        local is_request = request_offset ~= 0 and pos - data == request_offset
+        local header_len = 1 + (layout.variable or 0)
+        if is_request then
+            header_len = header_len + 6
+        end
+
+        if pos + header_len > footer then
+            tree:add(f.unknown, buffer(pos, footer - pos))
+            return
+        end
+
+        local length = layout.fixed
+        if length == nil then
+            length = buffer(pos + 1, layout.variable):le_uint()
+        end
+
+        local total = header_len + length
+        if pos + total > footer then
+            -- The element continues in the next fragment.
+            total = footer - pos
+        end
+
+        local name = element_names[id] or "Unknown"
+        local elt_tree = tree:add(proto, buffer(pos, total), string.format("Element: %s (0x%02X)", name, id))
+        elt_tree:add(f.element_id, buffer(pos, 1))
+        if layout.variable ~= nil then
+            elt_tree:add_le(f.element_length, buffer(pos + 1, layout.variable))
+        end
+
+        if is_request then
+            local request_pos = pos + header_len - 6
+            elt_tree:add_le(f.element_request_id, buffer(request_pos, 4))
+            elt_tree:add_le(f.element_next_request_offset, buffer(request_pos + 4, 2))
+            request_offset = buffer(request_pos + 4, 2):le_uint()
+        end
+
+        local data_len = total - header_len
+        if data_len > 0 then
+            local data_pos = pos + header_len
+            if id == REPLY_ID and data_len >= 4 then
+                elt_tree:add_le(f.element_reply_id, buffer(data_pos, 4))
+                data_pos = data_pos + 4
+                data_len = data_len - 4
+            end
+            if data_len > 0 then
+                elt_tree:add(f.element_data, buffer(data_pos, data_len))
+            end
+        end
+
+        pos = pos + total
+
+    end
+end
+
+function proto.dissector(buffer, pinfo, tree)
+
+    local len = buffer:len()
+    local data = 0
+    if HAS_PREFIX then
+        data = PREFIX_LEN
+    end
+
+    if len < data + 2 then
+        return 0
+    end
+
+    pinfo.cols.protocol = proto.name
+    local subtree = tree:add(proto, buffer(), proto.description)
+
+    if HAS_PREFIX then
+        subtree:add_le(f.prefix, buffer(0, PREFIX_LEN))
+    end
+
+    local flags = buffer(data, 2):le_uint()
+    local flags_tree = subtree:add_le(f.flags, buffer(data, 2))
+    for _, field in ipairs(flags_fields) do
+        flags_tree:add_le(field, buffer(data, 2))
+    end
+
+    local has_requests = has_flag(flags, FLAG_HAS_REQUESTS)
+    local has_seq = has_flag(flags, FLAG_HAS_SEQUENCE_NUMBER)
+    local has_checksum = has_flag(flags, FLAG_HAS_CHECKSUM)
+
+    local footer_len = 0
+    if has_checksum then footer_len = footer_len + 4 end
+    if has_seq then footer_len = footer_len + 12 end
+    if has_requests then footer_len = footer_len + 2 end
+
+    local footer = len - footer_len
+    if footer < data + 2 then
+        subtree:add_expert_info(PI_MALFORMED, PI_ERROR, "Packet too short for its footer")
+        return len
+    end
+
+    local pos = footer
+    local request_offset = 0
+    local seq_first = 0
+    local seq = 0
+    if has_seq then
+        seq_first = buffer(pos, 4):le_uint()
+        subtree:add_le(f.seq_first, buffer(pos, 4))
+        subtree:add_le(f.seq_last, buffer(pos + 4, 4))
+        pos = pos + 8
+    end
+    if has_requests then
+        request_offset = buffer(pos, 2):le_uint()
+        subtree:add_le(f.request_first_offset, buffer(pos, 2))
+        pos = pos + 2
+    end
+    if has_seq then
+        seq = buffer(pos, 4):le_uint()
+        subtree:add_le(f.seq, buffer(pos, 4))
+        pos = pos + 4
+    end
+    if has_checksum then
+        subtree:add_le(f.checksum, buffer(pos, 4))
+    end
+
+    local body = data + 2
+    if footer > body then
+        local body_tree = subtree:add(f.body, buffer(body, footer - body))
+        -- Only the first fragment of a bundle starts with an element.
+        if not has_seq or seq == seq_first then
+            dissect_elements(buffer, body_tree, data, body, footer, request_offset)
+        end
+    end
+
+    return len
+
+end
+"#;
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn lua_string() {
+        assert_eq!(LuaString("Login").to_string(), r#""Login""#);
+        assert_eq!(LuaString("a\"b\\c").to_string(), r#""a\"b\\c""#);
+        assert_eq!(LuaString("a\nb\rc\td").to_string(), r#""a\nb\rc\td""#);
+        assert_eq!(LuaString("\0\x7F1").to_string(), r#""\000\1271""#);
+        assert_eq!(LuaString("é").to_string(), r#""\195\169""#);
+    }
+
+    #[test]
+    fn write_elements() {
+
+        let mut dissector = LuaDissector::new("wgtk_test", "Test \"é\"\nApp");
+        dissector.add_port(20013);
+        dissector.add_element_raw(0x00, "Ping", ElementLength::Fixed(1));
+        dissector.add_element_raw(0x01, "Caf\u{E9} \"quoted\"", ElementLength::Variable16);
+
+        let mut output = Vec::new();
+        dissector.write(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let expected = r#"-- Wireshark dissector for Test "é" App, generated by wg-toolkit, do not edit.
+
+local proto = Proto("wgtk_test", "Test \"\195\169\"\nApp")
+
+local HAS_PREFIX = false
+local PREFIX_LEN = 4
+local REPLY_ID = 0xFF
+local FLAG_HAS_REQUESTS = 0x0001
+local FLAG_HAS_PIGGYBACKS = 0x0002
+local FLAG_HAS_ACKS = 0x0004
+local FLAG_ON_CHANNEL = 0x0008
+local FLAG_IS_RELIABLE = 0x0010
+local FLAG_IS_FRAGMENT = 0x0020
+local FLAG_HAS_SEQUENCE_NUMBER = 0x0040
+local FLAG_INDEXED_CHANNEL = 0x0080
+local FLAG_HAS_CHECKSUM = 0x0100
+local FLAG_CREATE_CHANNEL = 0x0200
+local FLAG_HAS_CUMULATIVE_ACK = 0x0400
+
+local element_names = {
+    [0x00] = "Ping",
+    [0x01] = "Caf\195\169 \"quoted\"",
+    [0xFF] = "Reply",
+}
+
+local element_layouts = {
+    [0x00] = { fixed = 1 },
+    [0x01] = { variable = 2 },
+    [0xFF] = { variable = 4 },
+}
+
+local f = {
+"#;
+
+        assert!(output.starts_with(expected), "{output}");
+        assert!(output.contains(r#"    has_sequence_number = ProtoField.bool("wgtk_test.flags.has_sequence_number", "Has sequence number", 16, nil, 0x0040),"#));
+        assert!(output.contains("    f.has_cumulative_ack,\n}"));
+        assert!(output.ends_with("udp_port:add(20013, proto)\n"));
+
+    }
+
+    #[test]
+    fn protocol_name() {
+        assert!(is_lowercase_ident("wgtk_login"));
+        assert!(is_lowercase_ident("_wgtk2"));
+        assert!(!is_lowercase_ident(""));
+        assert!(!is_lowercase_ident("2wgtk"));
+        assert!(!is_lowercase_ident("Wgtk"));
+        assert!(!is_lowercase_ident("wgtk-login"));
+        assert!(!is_lowercase_ident("wgtk\")"));
+    }
+
+    #[test]
+    #[should_panic = "invalid protocol name"]
+    fn protocol_name_invalid() {
+        LuaDissector::new("wgtk login", "Login App");
+    }
+
+    #[test]
+    fn flag_descriptions() {
+        assert_eq!(flag_description("HAS_REQUESTS"), "Has requests");
+        assert_eq!(flag_description("INDEXED_CHANNEL"), "Indexed channel");
+    }
+
+}
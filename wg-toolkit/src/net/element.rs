@@ -4,8 +4,14 @@ use std::io::{self, Read, Seek, Write};
 
 use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
 
+use encoding::{StringEncoding, InvalidCharPolicy};
+
 pub mod login;
 pub mod reply;
+pub mod encoding;
+
+#[cfg(feature = "serde")]
+pub mod json;
 
 
 pub trait ElementCodec {
@@ -78,6 +84,99 @@ impl ElementLength {
 }
 
 
+/// Description of an element known by an [`ElementLengthTable`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementDescriptor {
+    /// Name of the element, used for display.
+    pub name: String,
+    /// Length policy of the element.
+    pub length: ElementLength,
+}
+
+/// A table of the elements' length policies by identifier, this can be used to
+/// skip elements without knowing their codec, or to describe the protocol to
+/// external tools. Replies are not part of the table because their length is
+/// always encoded on 32 bits.
+#[derive(Debug, Clone)]
+pub struct ElementLengthTable {
+    elements: Vec<Option<ElementDescriptor>>,
+}
+
+impl Default for ElementLengthTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ElementLengthTable {
+
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self { elements: vec![None; 256] }
+    }
+
+    /// Create a table with all elements of the login app.
+    pub fn new_login() -> Self {
+        use login::{LoginCodec, ProbeCodec, PingCodec, ChallengeResponseCodec};
+        let mut table = Self::new();
+        table.insert_codec::<LoginCodec>(LoginCodec::ID, "Login");
+        table.insert_codec::<ProbeCodec>(ProbeCodec::ID, "Probe");
+        table.insert_codec::<PingCodec>(PingCodec::ID, "Ping");
+        table.insert_codec::<ChallengeResponseCodec>(ChallengeResponseCodec::ID, "ChallengeResponse");
+        table
+    }
+
+    /// Insert an element with an explicit length policy, replacing any element with
+    /// the same identifier.
+    pub fn insert<N: Into<String>>(&mut self, id: u8, name: N, length: ElementLength) {
+        self.elements[id as usize] = Some(ElementDescriptor { name: name.into(), length });
+    }
+
+    /// Insert an element with the length policy of the given codec.
+    #[inline]
+    pub fn insert_codec<E: ElementCodec>(&mut self, id: u8, name: &str) {
+        self.insert(id, name, E::LEN);
+    }
+
+    /// Remove an element from the table.
+    pub fn remove(&mut self, id: u8) -> Option<ElementDescriptor> {
+        self.elements[id as usize].take()
+    }
+
+    /// Get the description of an element.
+    #[inline]
+    pub fn get(&self, id: u8) -> Option<&ElementDescriptor> {
+        self.elements[id as usize].as_ref()
+    }
+
+    /// Get the length policy of an element.
+    #[inline]
+    pub fn get_length(&self, id: u8) -> Option<ElementLength> {
+        self.get(id).map(|desc| desc.length)
+    }
+
+    /// Iterate over all known elements, ordered by identifier.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &'_ ElementDescriptor)> + '_ {
+        self.elements.iter()
+            .enumerate()
+            .filter_map(|(id, desc)| desc.as_ref().map(|desc| (id as u8, desc)))
+    }
+
+}
+
+
+/// Maximum value of a packed 32-bits integer, values up to 254 are encoded on a
+/// single byte, larger values are encoded as a `0xFF` byte followed by the value
+/// on 24 bits.
+pub const PACKED_U32_MAX: u32 = 0xFFFFFF;
+
+/// Return the number of bytes used to encode the given packed 32-bits integer.
+#[inline]
+pub fn packed_u32_len(n: u32) -> usize {
+    if n >= 255 { 4 } else { 1 }
+}
+
+
 /// A extension trait for `Read` specific to element decoding.
 pub trait ElementReadExt: Read {
 
@@ -89,13 +188,18 @@ pub trait ElementReadExt: Read {
         }
     }
 
+    /// Read a blob of data with its packed length before. The buffer is not
+    /// allocated upfront, so an invalid length only fails with an unexpected EOF.
     fn read_rich_blob(&mut self) -> io::Result<Vec<u8>> {
         let len = self.read_packed_u32()? as usize;
-        let mut buf = vec![0; len];
-        self.read_exact(&mut buf[..])?;
+        let mut buf = Vec::new();
+        if self.take(len as u64).read_to_end(&mut buf)? != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
         Ok(buf)
     }
 
+    /// Read a UTF-8 string with its packed length before.
     fn read_rich_string(&mut self) -> io::Result<String> {
         let blob = self.read_rich_blob()?;
         match String::from_utf8(blob) {
@@ -104,15 +208,23 @@ pub trait ElementReadExt: Read {
         }
     }
 
+    /// Read a string with its packed length before, decoded with the given encoding.
+    fn read_rich_string_encoded(&mut self, encoding: StringEncoding, policy: InvalidCharPolicy) -> io::Result<String> {
+        encoding.decode(self.read_rich_blob()?, policy)
+    }
+
 }
 
 
 /// A extension trait for `Write` specific to element encoding.
 pub trait ElementWriteExt: Write {
 
-    /// Write a packed 32-bits integer.
+    /// Write a packed 32-bits integer, an invalid input error is returned if the
+    /// integer is greater than [`PACKED_U32_MAX`].
     fn write_packed_u32(&mut self, n: u32) -> io::Result<()> {
-        if n >= 255 {
+        if n > PACKED_U32_MAX {
+            Err(io::ErrorKind::InvalidInput.into())
+        } else if n >= 255 {
             self.write_u8(255)?;
             self.write_u24::<LittleEndian>(n)
         } else {
@@ -122,7 +234,8 @@ pub trait ElementWriteExt: Write {
 
     /// Write a blob of data with its packed length before.
     fn write_rich_blob(&mut self, data: &[u8]) -> io::Result<()> {
-        self.write_packed_u32(data.len() as u32)?;
+        let len = u32::try_from(data.len()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        self.write_packed_u32(len)?;
         self.write_all(data)
     }
 
@@ -131,6 +244,11 @@ pub trait ElementWriteExt: Write {
         self.write_rich_blob(s.as_bytes())
     }
 
+    /// Write a string with its packed length before, encoded with the given encoding.
+    fn write_rich_string_encoded(&mut self, s: &str, encoding: StringEncoding, policy: InvalidCharPolicy) -> io::Result<()> {
+        self.write_rich_blob(&encoding.encode(s, policy)?)
+    }
+
 }
 
 impl<R: Read> ElementReadExt for R {}
@@ -150,8 +268,8 @@ impl<I: RawElementCodecLen> ElementCodec for RawElementCodec<I> {
         write.write_all(&input[..])
     }
 
-    fn decode<R: Read + Seek>(&self, mut read: R, len: u64) -> io::Result<Self::Element> {
-        let mut buf = Vec::with_capacity(len as usize);
+    fn decode<R: Read + Seek>(&self, mut read: R, _len: u64) -> io::Result<Self::Element> {
+        let mut buf = Vec::new();
         read.read_to_end(&mut buf)?;
         Ok(buf)
     }
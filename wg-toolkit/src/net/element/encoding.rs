@@ -0,0 +1,217 @@
+//! Text encodings used by string fields of elements.
+//!
+//! Most fields are encoded in UTF-8, but some localized fields, such as
+//! server messages or ban reasons, are sent in UTF-16LE or in a legacy
+//! single-byte code page.
+
+use std::io;
+
+
+/// Encoding of a string field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringEncoding {
+    /// UTF-8, the default encoding.
+    #[default]
+    Utf8,
+    /// UTF-16 little endian, the length of the field is given in bytes.
+    Utf16Le,
+    /// ISO-8859-1, each byte is the code point.
+    Latin1,
+    /// Windows-1251 code page, used for cyrillic text.
+    Windows1251,
+}
+
+/// Policy applied when a string can't be represented in its encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidCharPolicy {
+    /// Return an invalid data error when decoding, or an invalid input error
+    /// when encoding.
+    #[default]
+    Error,
+    /// Replace invalid characters with U+FFFD when decoding, or with `?`
+    /// when encoding.
+    Replace,
+}
+
+impl StringEncoding {
+
+    /// Decode the given bytes into a string.
+    pub fn decode(self, bytes: Vec<u8>, policy: InvalidCharPolicy) -> io::Result<String> {
+        match (self, policy) {
+            (Self::Utf8, InvalidCharPolicy::Error) => {
+                String::from_utf8(bytes).map_err(|_| io::ErrorKind::InvalidData.into())
+            }
+            (Self::Utf8, InvalidCharPolicy::Replace) => {
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            (Self::Utf16Le, _) => {
+                // An odd trailing byte is an invalid character.
+                let mut chunks = bytes.chunks_exact(2);
+                let units = chunks.by_ref().map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+                let mut s = String::with_capacity(bytes.len() / 2);
+                for c in char::decode_utf16(units) {
+                    s.push(replace_char(c.ok(), policy)?);
+                }
+                if !chunks.remainder().is_empty() {
+                    s.push(replace_char(None, policy)?);
+                }
+                Ok(s)
+            }
+            (Self::Latin1, _) => {
+                Ok(bytes.iter().map(|&b| b as char).collect())
+            }
+            (Self::Windows1251, _) => {
+                bytes.iter()
+                    .map(|&b| replace_char(windows1251_to_char(b), policy))
+                    .collect()
+            }
+        }
+    }
+
+    /// Encode the given string into bytes.
+    pub fn encode(self, s: &str, policy: InvalidCharPolicy) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Utf8 => Ok(s.as_bytes().to_vec()),
+            Self::Utf16Le => {
+                Ok(s.encode_utf16().flat_map(u16::to_le_bytes).collect())
+            }
+            Self::Latin1 => {
+                s.chars()
+                    .map(|c| replace_byte(u8::try_from(c).ok(), policy))
+                    .collect()
+            }
+            Self::Windows1251 => {
+                s.chars()
+                    .map(|c| replace_byte(char_to_windows1251(c), policy))
+                    .collect()
+            }
+        }
+    }
+
+}
+
+
+/// Upper half of the Windows-1251 code page, the byte 0x98 is undefined.
+const WINDOWS1251_HIGH: [u16; 64] = [
+    0x0402, 0x0403, 0x201A, 0x0453, 0x201E, 0x2026, 0x2020, 0x2021,
+    0x20AC, 0x2030, 0x0409, 0x2039, 0x040A, 0x040C, 0x040B, 0x040F,
+    0x0452, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x0000, 0x2122, 0x0459, 0x203A, 0x045A, 0x045C, 0x045B, 0x045F,
+    0x00A0, 0x040E, 0x045E, 0x0408, 0x00A4, 0x0490, 0x00A6, 0x00A7,
+    0x0401, 0x00A9, 0x0404, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x0407,
+    0x00B0, 0x00B1, 0x0406, 0x0456, 0x0491, 0x00B5, 0x00B6, 0x00B7,
+    0x0451, 0x2116, 0x0454, 0x00BB, 0x0458, 0x0405, 0x0455, 0x0457,
+];
+
+/// Internal function to decode a single Windows-1251 byte.
+fn windows1251_to_char(b: u8) -> Option<char> {
+    match b {
+        0x00..=0x7F => Some(b as char),
+        0x80..=0xBF => match WINDOWS1251_HIGH[(b - 0x80) as usize] {
+            0 => None,
+            c => char::from_u32(c as u32),
+        },
+        // Cyrillic letters from А to я are contiguous.
+        0xC0..=0xFF => char::from_u32(0x0410 + (b - 0xC0) as u32),
+    }
+}
+
+/// Internal function to encode a single character in Windows-1251.
+fn char_to_windows1251(c: char) -> Option<u8> {
+    match c as u32 {
+        n @ 0x00..=0x7F => Some(n as u8),
+        n @ 0x0410..=0x044F => Some((n - 0x0410) as u8 + 0xC0),
+        n => WINDOWS1251_HIGH.iter()
+            .position(|&high| high != 0 && high as u32 == n)
+            .map(|index| index as u8 + 0x80),
+    }
+}
+
+/// Internal function to apply the policy to a decoded character.
+fn replace_char(c: Option<char>, policy: InvalidCharPolicy) -> io::Result<char> {
+    match (c, policy) {
+        (Some(c), _) => Ok(c),
+        (None, InvalidCharPolicy::Replace) => Ok(char::REPLACEMENT_CHARACTER),
+        (None, InvalidCharPolicy::Error) => Err(io::ErrorKind::InvalidData.into()),
+    }
+}
+
+/// Internal function to apply the policy to an encoded byte.
+fn replace_byte(b: Option<u8>, policy: InvalidCharPolicy) -> io::Result<u8> {
+    match (b, policy) {
+        (Some(b), _) => Ok(b),
+        (None, InvalidCharPolicy::Replace) => Ok(b'?'),
+        (None, InvalidCharPolicy::Error) => Err(io::ErrorKind::InvalidInput.into()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn utf8() {
+        let bytes = "Привет".as_bytes().to_vec();
+        assert_eq!(StringEncoding::Utf8.encode("Привет", InvalidCharPolicy::Error).unwrap(), bytes);
+        assert_eq!(StringEncoding::Utf8.decode(bytes, InvalidCharPolicy::Error).unwrap(), "Привет");
+        assert_eq!(StringEncoding::Utf8.decode(vec![b'a', 0xFF], InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(StringEncoding::Utf8.decode(vec![b'a', 0xFF], InvalidCharPolicy::Replace).unwrap(), "a\u{FFFD}");
+    }
+
+    #[test]
+    fn utf16le() {
+
+        let bytes = vec![0x1F, 0x04, 0x40, 0x04, 0x3D, 0xD8, 0x00, 0xDE];
+        assert_eq!(StringEncoding::Utf16Le.encode("Пр😀", InvalidCharPolicy::Error).unwrap(), bytes);
+        assert_eq!(StringEncoding::Utf16Le.decode(bytes, InvalidCharPolicy::Error).unwrap(), "Пр😀");
+
+        // Odd trailing byte.
+        let bytes = vec![0x1F, 0x04, 0x40];
+        assert_eq!(StringEncoding::Utf16Le.decode(bytes.clone(), InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(StringEncoding::Utf16Le.decode(bytes, InvalidCharPolicy::Replace).unwrap(), "П\u{FFFD}");
+
+        // Lone surrogate.
+        let bytes = vec![0x3D, 0xD8, 0x41, 0x00];
+        assert_eq!(StringEncoding::Utf16Le.decode(bytes.clone(), InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(StringEncoding::Utf16Le.decode(bytes, InvalidCharPolicy::Replace).unwrap(), "\u{FFFD}A");
+
+    }
+
+    #[test]
+    fn latin1() {
+        assert_eq!(StringEncoding::Latin1.encode("café", InvalidCharPolicy::Error).unwrap(), b"caf\xE9");
+        assert_eq!(StringEncoding::Latin1.decode(b"caf\xE9".to_vec(), InvalidCharPolicy::Error).unwrap(), "café");
+        assert_eq!(StringEncoding::Latin1.encode("€", InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(StringEncoding::Latin1.encode("a€", InvalidCharPolicy::Replace).unwrap(), b"a?");
+    }
+
+    #[test]
+    fn windows1251() {
+
+        // Every defined byte round trips through its character.
+        for b in 0..=255u8 {
+            match windows1251_to_char(b) {
+                Some(c) => assert_eq!(char_to_windows1251(c), Some(b), "byte {b:#04X}"),
+                None => assert_eq!(b, 0x98),
+            }
+        }
+
+        let bytes = b"\xCF\xF0\xE8\xE2\xE5\xF2 \xA8\xB8 \x88 \xB9".to_vec();
+        assert_eq!(StringEncoding::Windows1251.encode("Привет Ёё € №", InvalidCharPolicy::Error).unwrap(), bytes);
+        assert_eq!(StringEncoding::Windows1251.decode(bytes, InvalidCharPolicy::Error).unwrap(), "Привет Ёё € №");
+
+        // Undefined byte.
+        assert_eq!(StringEncoding::Windows1251.decode(vec![b'a', 0x98], InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(StringEncoding::Windows1251.decode(vec![b'a', 0x98], InvalidCharPolicy::Replace).unwrap(), "a\u{FFFD}");
+
+        // Unrepresentable character.
+        assert_eq!(StringEncoding::Windows1251.encode("é", InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(StringEncoding::Windows1251.encode("aé", InvalidCharPolicy::Replace).unwrap(), b"a?");
+
+    }
+
+}
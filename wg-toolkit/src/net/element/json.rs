@@ -0,0 +1,117 @@
+//! Newline-delimited JSON export of decoded elements.
+//!
+//! This is intended for analysis of decoded sessions with external tools
+//! such as `jq` or `pandas`, each element is written as a single JSON
+//! object on its own line.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::net::bundle::Element;
+
+
+/// A writer that converts decoded elements into newline-delimited JSON.
+///
+/// Each line is an object of the form `{"id":0,"request_id":1,"element":{..}}`
+/// for simple elements, and `{"reply_to":1,"request_id":null,"element":{..}}`
+/// for replies.
+pub struct ElementJsonWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ElementJsonWriter<W> {
+
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Write a simple element decoded with the given ID.
+    pub fn write_element<E: Serialize>(&mut self, id: u8, elt: &Element<E>) -> io::Result<()> {
+        self.write_line(&ElementLine {
+            id: Some(id),
+            reply_to: None,
+            request_id: elt.request_id,
+            element: &elt.element,
+        })
+    }
+
+    /// Write a reply element, decoded for the given request ID.
+    pub fn write_reply<E: Serialize>(&mut self, request_id: u32, elt: &Element<E>) -> io::Result<()> {
+        self.write_line(&ElementLine {
+            id: None,
+            reply_to: Some(request_id),
+            request_id: elt.request_id,
+            element: &elt.element,
+        })
+    }
+
+    /// Flush the underlying writer.
+    #[inline]
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Internal function to write a single line of JSON.
+    fn write_line<T: Serialize>(&mut self, line: &T) -> io::Result<()> {
+        serde_json::to_writer(&mut self.inner, line)?;
+        self.inner.write_all(b"\n")
+    }
+
+}
+
+
+/// Internal structure of a single JSON line.
+#[derive(Serialize)]
+struct ElementLine<'a, E> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<u32>,
+    request_id: Option<u32>,
+    element: &'a E,
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::net::element::login::LoginParams;
+
+    #[test]
+    fn login_secrets_not_exported() {
+
+        let login = LoginParams {
+            username: "user".to_string(),
+            password: "hunter2".to_string().into(),
+            blowfish_key: vec![0xDE, 0xAD, 0xBE, 0xEF].into(),
+            ..Default::default()
+        };
+
+        let mut writer = ElementJsonWriter::new(Vec::new());
+        writer.write_element(0x00, &Element { request_id: Some(1), element: login }).unwrap();
+        let line = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert!(line.contains("\"username\":\"user\""));
+        assert!(!line.contains("password"));
+        assert!(!line.contains("hunter2"));
+        assert!(!line.contains("blowfish_key"));
+
+    }
+
+    #[test]
+    fn login_secrets_not_debugged() {
+        let login = LoginParams {
+            password: "hunter2".to_string().into(),
+            ..Default::default()
+        };
+        assert!(!format!("{login:?}").contains("hunter2"));
+    }
+
+}
@@ -1,30 +1,67 @@
 //! Definition of all predefined
 
 use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::cell::RefCell;
+use std::fmt;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand::rngs::StdRng;
 use rsa::{RsaPrivateKey, RsaPublicKey};
+use zeroize::Zeroizing;
 
 use super::{ElementCodec, ElementLength, ElementReadExt, ElementWriteExt};
+use super::encoding::{StringEncoding, InvalidCharPolicy};
 use crate::net::filter::{RsaReader, RsaWriter};
 
 
-/// A login request, optionally encrypted.
-#[derive(Debug, Default)]
+/// A login request, optionally encrypted. The password and the Blowfish key
+/// are secrets, so they are neither serialized nor printed in debug output,
+/// and they are zeroized when dropped.
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LoginParams {
     pub version: u32,
     pub username: String,
-    pub password: String,
-    pub blowfish_key: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing, default, deserialize_with = "deserialize_secret"))]
+    pub password: Zeroizing<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing, default, deserialize_with = "deserialize_secret"))]
+    pub blowfish_key: Zeroizing<Vec<u8>>,
     pub context: String,
     pub digest: Option<[u8; 16]>,
     pub nonce: u32,
     //pub data: Vec<u8>
 }
 
+impl fmt::Debug for LoginParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginParams")
+            .field("version", &self.version)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("blowfish_key", &"<redacted>")
+            .field("context", &self.context)
+            .field("digest", &self.digest)
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
+/// Deserialize a secret directly into its zeroizing wrapper.
+#[cfg(feature = "serde")]
+fn deserialize_secret<'de, D, T>(deserializer: D) -> Result<Zeroizing<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de> + zeroize::Zeroize,
+{
+    T::deserialize(deserializer).map(Zeroizing::new)
+}
+
 pub struct LoginCodec<'ek, 'dk> {
     encode_key: Option<&'ek RsaPublicKey>,
-    decode_key: &'dk RsaPrivateKey
+    decode_key: &'dk RsaPrivateKey,
+    /// Optional random generator used for encryption, the OS one is used
+    /// if not specified.
+    rng: Option<RefCell<StdRng>>,
 }
 
 impl<'ek, 'dk> LoginCodec<'ek, 'dk> {
@@ -32,7 +69,14 @@ impl<'ek, 'dk> LoginCodec<'ek, 'dk> {
     pub const ID: u8 = 0x00;
 
     pub fn new(encode_key: Option<&'ek RsaPublicKey>, decode_key: &'dk RsaPrivateKey) -> Self {
-        Self { encode_key, decode_key }
+        Self { encode_key, decode_key, rng: None }
+    }
+
+    /// Use the given random generator for encryption instead of the OS one,
+    /// a seeded generator can be used to get reproducible encrypted logins.
+    pub fn with_rng(mut self, rng: StdRng) -> Self {
+        self.rng = Some(RefCell::new(rng));
+        self
     }
 
     pub fn new_clear(decode_key: &'dk RsaPrivateKey) -> Self {
@@ -61,8 +105,8 @@ impl<'ek, 'dk> LoginCodec<'ek, 'dk> {
         Ok(LoginParams {
             version,
             username: input.read_rich_string()?,
-            password: input.read_rich_string()?,
-            blowfish_key: input.read_rich_blob()?,
+            password: Zeroizing::new(input.read_rich_string()?),
+            blowfish_key: Zeroizing::new(input.read_rich_blob()?),
             context: input.read_rich_string()?,
             digest: if flags & 0x01 != 0 {
                 let mut digest = [0; 16];
@@ -94,7 +138,10 @@ impl ElementCodec for LoginCodec<'_, '_> {
         write.write_u32::<LittleEndian>(input.version)?;
         if let Some(key) = self.encode_key {
             write.write_u8(1)?;
-            Self::encode_internal(RsaWriter::new(write, key), input)
+            match &self.rng {
+                Some(rng) => Self::encode_internal(RsaWriter::with_rng(write, key, &mut *rng.borrow_mut()), input),
+                None => Self::encode_internal(RsaWriter::new(write, key), input),
+            }
         } else {
             write.write_u8(0)?;
             Self::encode_internal(write, input)
@@ -127,6 +174,7 @@ impl ElementCodec for LoginCodec<'_, '_> {
 
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Challenge {
     pub kind: String,
     pub key: String
@@ -194,3 +242,296 @@ impl ElementCodec for PingCodec {
         read.read_u8()
     }
 }
+
+
+/// The probe element, sent by launchers before login to get the server's
+/// status, the server answers with a reply containing a [`ProbeReply`].
+pub struct ProbeCodec;
+
+impl ProbeCodec {
+    pub const ID: u8 = 0x01;
+}
+
+impl ElementCodec for ProbeCodec {
+    const LEN: ElementLength = ElementLength::Fixed(0);
+    type Element = ();
+    fn encode<W: Write>(&self, _write: W, _input: Self::Element) -> io::Result<()> {
+        Ok(())
+    }
+    fn decode<R: Read + Seek>(&self, _read: R, _len: u64) -> io::Result<Self::Element> {
+        Ok(())
+    }
+}
+
+
+/// The reply to a probe, this is a list of key/value strings describing the
+/// server, see `PROBE_KEY_*` constants for known keys.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeReply {
+    pub entries: Vec<(String, String)>,
+}
+
+impl ProbeReply {
+
+    pub const PROBE_KEY_HOST_NAME: &'static str = "hostName";
+    pub const PROBE_KEY_OWNER_NAME: &'static str = "ownerName";
+    pub const PROBE_KEY_USERS_COUNT: &'static str = "usersCount";
+    pub const PROBE_KEY_UNIVERSE_NAME: &'static str = "universeName";
+    pub const PROBE_KEY_SPACE_NAME: &'static str = "spaceName";
+    pub const PROBE_KEY_BINARY_ID: &'static str = "binaryID";
+
+    /// Add a key/value entry to this reply.
+    pub fn add<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.entries.push((key.into(), value.into()));
+    }
+
+    /// Get the value of the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find_map(|(k, v)| (k == key).then_some(v.as_str()))
+    }
+
+}
+
+pub struct ProbeReplyCodec;
+
+impl ElementCodec for ProbeReplyCodec {
+
+    const LEN: ElementLength = ElementLength::Variable32;
+    type Element = ProbeReply;
+
+    fn encode<W: Write>(&self, mut write: W, input: Self::Element) -> io::Result<()> {
+        for (key, value) in &input.entries {
+            write.write_rich_string(key)?;
+            write.write_rich_string(value)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read + Seek>(&self, read: R, len: u64) -> io::Result<Self::Element> {
+        let mut read = read.take(len);
+        let mut reply = ProbeReply::default();
+        while read.limit() != 0 {
+            let key = read.read_rich_string()?;
+            let value = read.read_rich_string()?;
+            reply.entries.push((key, value));
+        }
+        Ok(reply)
+    }
+
+}
+
+
+/// The server message sent to the client on successful login, it usually carries
+/// a JSON dictionary (for example with the peripheries or the required client
+/// version), but it may be any string. JSON dictionaries are only parsed when the
+/// `serde` feature is enabled, any other message is kept raw, this enumeration is
+/// therefore non exhaustive so that enabling the feature is additive.
+///
+/// Localized messages, such as ban reasons, may be sent in UTF-16LE or in a
+/// legacy code page, they can be read and written with [`Self::read_encoded`]
+/// and [`Self::write_encoded`].
+///
+/// There is no codec for the login reply yet, because it is encrypted with the
+/// session's Blowfish key and its layout is not known by this crate. The message
+/// can be read and written with [`Self::read`] and [`Self::write`] from the
+/// decrypted reply data.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ServerMessage {
+    /// A message that is not a JSON dictionary.
+    Raw(String),
+    /// A JSON dictionary.
+    #[cfg(feature = "serde")]
+    Json(serde_json::Map<String, serde_json::Value>),
+}
+
+impl Default for ServerMessage {
+    fn default() -> Self {
+        Self::Raw(String::new())
+    }
+}
+
+impl ServerMessage {
+
+    /// Parse a server message, falling back to a raw message if it's not in a
+    /// known format.
+    pub fn parse(message: String) -> Self {
+        #[cfg(feature = "serde")]
+        if let Ok(map) = serde_json::from_str(&message) {
+            return Self::Json(map);
+        }
+        Self::Raw(message)
+    }
+
+    /// Get the value of a key if the message is a JSON dictionary.
+    #[cfg(feature = "serde")]
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        match self {
+            Self::Json(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Encode the message back to the string sent on the wire.
+    pub fn to_wire_string(&self) -> String {
+        match self {
+            Self::Raw(message) => message.clone(),
+            #[cfg(feature = "serde")]
+            Self::Json(map) => serde_json::to_string(map).unwrap(),
+        }
+    }
+
+    /// Read and parse a server message, as a UTF-8 string with a packed length prefix.
+    pub fn read<R: Read>(read: R) -> io::Result<Self> {
+        Self::read_encoded(read, StringEncoding::Utf8, InvalidCharPolicy::Error)
+    }
+
+    /// Read and parse a server message, as a string with a packed length prefix
+    /// decoded with the given encoding.
+    pub fn read_encoded<R: Read>(mut read: R, encoding: StringEncoding, policy: InvalidCharPolicy) -> io::Result<Self> {
+        Ok(Self::parse(read.read_rich_string_encoded(encoding, policy)?))
+    }
+
+    /// Write the server message, as a UTF-8 string with a packed length prefix.
+    pub fn write<W: Write>(&self, write: W) -> io::Result<()> {
+        self.write_encoded(write, StringEncoding::Utf8, InvalidCharPolicy::Error)
+    }
+
+    /// Write the server message, as a string with a packed length prefix encoded
+    /// with the given encoding.
+    pub fn write_encoded<W: Write>(&self, mut write: W, encoding: StringEncoding, policy: InvalidCharPolicy) -> io::Result<()> {
+        write.write_rich_string_encoded(&self.to_wire_string(), encoding, policy)
+    }
+
+}
+
+/// A builder for JSON dictionary server messages, for servers to populate the
+/// message sent on successful login.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default)]
+pub struct ServerMessageBuilder {
+    map: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "serde")]
+impl ServerMessageBuilder {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value of a key, replacing any previous value.
+    pub fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<serde_json::Value>,
+    {
+        self.map.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> ServerMessage {
+        ServerMessage::Json(self.map)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn server_message_raw() {
+        let mut data = Vec::new();
+        ServerMessage::Raw("hello".to_string()).write(&mut data).unwrap();
+        assert_eq!(data, b"\x05hello");
+        assert_eq!(ServerMessage::read(&data[..]).unwrap(), ServerMessage::Raw("hello".to_string()));
+    }
+
+    #[test]
+    fn server_message_encoded() {
+
+        let message = ServerMessage::Raw("Бан".to_string());
+
+        let mut data = Vec::new();
+        message.write_encoded(&mut data, StringEncoding::Windows1251, InvalidCharPolicy::Error).unwrap();
+        assert_eq!(data, b"\x03\xC1\xE0\xED");
+        assert_eq!(ServerMessage::read_encoded(&data[..], StringEncoding::Windows1251, InvalidCharPolicy::Error).unwrap(), message);
+        // Cyrillic in a legacy code page isn't valid UTF-8.
+        assert_eq!(ServerMessage::read(&data[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut data = Vec::new();
+        message.write_encoded(&mut data, StringEncoding::Utf16Le, InvalidCharPolicy::Error).unwrap();
+        assert_eq!(data, b"\x06\x11\x04\x30\x04\x3D\x04");
+        assert_eq!(ServerMessage::read_encoded(&data[..], StringEncoding::Utf16Le, InvalidCharPolicy::Error).unwrap(), message);
+
+        // Unrepresentable characters.
+        let message = ServerMessage::Raw("Бан 禁止".to_string());
+        let mut data = Vec::new();
+        assert_eq!(message.write_encoded(&mut data, StringEncoding::Windows1251, InvalidCharPolicy::Error).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let mut data = Vec::new();
+        message.write_encoded(&mut data, StringEncoding::Windows1251, InvalidCharPolicy::Replace).unwrap();
+        assert_eq!(data, b"\x06\xC1\xE0\xED ??");
+
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn server_message_json() {
+
+        let message = ServerMessageBuilder::new()
+            .with("peripheries", vec![101, 102])
+            .with("version", "1.0")
+            .build();
+
+        let mut data = Vec::new();
+        message.write(&mut data).unwrap();
+
+        let read = ServerMessage::read(&data[..]).unwrap();
+        assert_eq!(read, message);
+        assert_eq!(read.get("version").and_then(|v| v.as_str()), Some("1.0"));
+
+        // Only dictionaries are parsed.
+        assert_eq!(ServerMessage::parse("[1, 2]".to_string()), ServerMessage::Raw("[1, 2]".to_string()));
+
+    }
+
+    #[test]
+    fn login_encrypted() {
+
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let private_key = RsaPrivateKey::new(&mut rng, 512).unwrap();
+        let public_key = RsaPublicKey::from(&private_key);
+        let codec = LoginCodec::new_encrypted(&public_key, &private_key).with_rng(rng);
+
+        let login = LoginParams {
+            version: 42,
+            username: "user".to_string(),
+            password: "hunter2".to_string().into(),
+            blowfish_key: vec![0xDE, 0xAD, 0xBE, 0xEF].into(),
+            nonce: 0x1234,
+            ..Default::default()
+        };
+
+        let mut data = Vec::new();
+        codec.encode(&mut data, login).unwrap();
+        let len = data.len() as u64;
+        let login = codec.decode(io::Cursor::new(data), len).unwrap();
+
+        // Without a drop implementation, fields can be moved out.
+        let LoginParams { version, username, password, blowfish_key, nonce, .. } = login;
+        assert_eq!(version, 42);
+        assert_eq!(username, "user");
+        assert_eq!(password.as_str(), "hunter2");
+        assert_eq!(&blowfish_key[..], &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(nonce, 0x1234);
+
+    }
+
+}
@@ -66,6 +66,7 @@ impl<C: ElementCodec> ElementCodec for ReplyCodec<'_, C> {
 
 /// A wrapper for a reply element, with the request ID.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Reply<E> {
     /// The request ID this reply is for.
     pub request_id: u32,
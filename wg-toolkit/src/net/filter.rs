@@ -2,9 +2,11 @@
 
 use std::io::{self, Read, Write};
 use rand::rngs::OsRng;
+use rand::{RngCore, CryptoRng};
 
 use rsa::{RsaPrivateKey, PublicKeyParts, PaddingScheme, RsaPublicKey, PublicKey};
 use sha1::Sha1;
+use zeroize::Zeroize;
 
 
 /// A filter reader for RSA-encrypted data (its length must be
@@ -39,6 +41,7 @@ impl<'a, R: Read> Read for RsaReader<'a, R> {
             match self.inner.read_exact(&mut self.cipher_block[..]) {
                 Ok(()) => {
                     let scheme = PaddingScheme::new_oaep::<Sha1>();
+                    self.clear_block.zeroize();
                     self.clear_block = self.key.decrypt(scheme, &self.cipher_block[..]).unwrap();
                     self.pos = 0;
                 }
@@ -63,28 +66,50 @@ impl<'a, R: Read> Read for RsaReader<'a, R> {
 }
 
 
+impl<R: Read> Drop for RsaReader<'_, R> {
+    fn drop(&mut self) {
+        // Clear data may contain secrets, such as login passwords.
+        self.clear_block.zeroize();
+    }
+}
+
+
 /// A filter write for clear data to RSA-encrypted blocks.
 /// Note that each flush call with non-empty internal buffer
 /// will write a full RSA block.
-pub struct RsaWriter<'a, O: Write> {
+/// 
+/// The random generator used for padding defaults to the OS one,
+/// a seeded generator can be given to get reproducible blocks.
+pub struct RsaWriter<'a, O: Write, G: RngCore + CryptoRng = OsRng> {
     inner: O,
     key: &'a RsaPublicKey,
+    rng: G,
     clear_block: Vec<u8>,
     clear_block_cap: usize
 }
 
 impl<'a, O: Write> RsaWriter<'a, O> {
     pub fn new(inner: O, key: &'a RsaPublicKey) -> Self {
+        Self::with_rng(inner, key, OsRng)
+    }
+}
+
+impl<'a, O: Write, G: RngCore + CryptoRng> RsaWriter<'a, O, G> {
+    pub fn with_rng(inner: O, key: &'a RsaPublicKey, rng: G) -> Self {
+        // The clear block is reserved once, so it never reallocates and
+        // leaves no copy of the clear data behind.
+        let clear_block_cap = key.size() - 41 - 1; // key.size() - 130
         Self {
             inner,
-            clear_block: Vec::new(),
-            clear_block_cap: key.size() - 41 - 1, // key.size() - 130,
+            clear_block: Vec::with_capacity(clear_block_cap),
+            clear_block_cap,
             key,
+            rng,
         }
     }
 }
 
-impl<O: Write> Write for RsaWriter<'_, O> {
+impl<O: Write, G: RngCore + CryptoRng> Write for RsaWriter<'_, O, G> {
 
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let remaining = self.clear_block_cap - self.clear_block.len();
@@ -101,17 +126,54 @@ impl<O: Write> Write for RsaWriter<'_, O> {
     fn flush(&mut self) -> io::Result<()> {
         if !self.clear_block.is_empty() {
             let padding = PaddingScheme::new_oaep::<Sha1>();
-            let cipher_block = self.key.encrypt(&mut OsRng, padding, &self.clear_block[..]).unwrap();
+            let cipher_block = self.key.encrypt(&mut self.rng, padding, &self.clear_block[..]).unwrap();
+            self.clear_block.zeroize();
             self.inner.write_all(&cipher_block[..])?;
-            self.clear_block.clear();
         }
         Ok(())
     }
 
 }
 
-impl<O: Write> Drop for RsaWriter<'_, O> {
+impl<O: Write, G: RngCore + CryptoRng> Drop for RsaWriter<'_, O, G> {
     fn drop(&mut self) {
         let _ = Write::flush(self);
+        self.clear_block.zeroize();
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn rsa_round_trip() {
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let private_key = RsaPrivateKey::new(&mut rng, 512).unwrap();
+        let public_key = RsaPublicKey::from(&private_key);
+        let clear = (0..100).collect::<Vec<u8>>();
+
+        let mut cipher = Vec::new();
+        let mut writer = RsaWriter::with_rng(&mut cipher, &public_key, rng);
+        let block_ptr = writer.clear_block.as_ptr();
+        for chunk in clear.chunks(7) {
+            writer.write_all(chunk).unwrap();
+            // The clear block is never reallocated.
+            assert_eq!(writer.clear_block.as_ptr(), block_ptr);
+            assert_eq!(writer.clear_block.capacity(), writer.clear_block_cap);
+        }
+        drop(writer);
+        assert_eq!(cipher.len() % public_key.size(), 0);
+
+        let mut read = Vec::new();
+        RsaReader::new(&cipher[..], &private_key).read_to_end(&mut read).unwrap();
+        assert_eq!(read, clear);
+
+    }
+
+}
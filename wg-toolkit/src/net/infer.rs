@@ -0,0 +1,299 @@
+//! Heuristic inference of elements' field layouts from example payloads,
+//! such as the samples collected by [`CorpusStats`], producing draft Rust
+//! structures to speed up the implementation of new codecs.
+//!
+//! The inference only detects strings with packed length prefixes, arrays
+//! of same-size records with a packed count prefix and ending the payloads,
+//! and fixed-width integers, the result is a starting point that must be
+//! checked by hand.
+//!
+//! [`CorpusStats`]: super::corpus::CorpusStats
+
+use std::io::{self, Write};
+
+use super::element::ElementReadExt;
+
+
+/// Minimum length of the longest string for a field to be considered a
+/// string, this avoids detecting single printable bytes as strings.
+const MIN_STRING_LEN: usize = 2;
+
+
+/// A field inferred from example payloads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InferredField {
+    /// A little endian unsigned integer of the given width in bytes (1, 2
+    /// or 4), with its value if it is the same in all payloads.
+    Int {
+        width: u8,
+        constant: Option<u32>,
+    },
+    /// A string with a packed length prefix.
+    String,
+    /// An array with a packed count prefix, ending the payload, of records
+    /// of the same size with the given fields.
+    Array {
+        fields: Vec<InferredField>,
+    },
+    /// Remaining data that can't be inferred, because payloads have
+    /// different lengths.
+    Remaining,
+}
+
+
+/// Infer the layout of fields from example payloads of the same element.
+pub fn infer_layout(samples: &[Vec<u8>]) -> Vec<InferredField> {
+
+    let mut fields = Vec::new();
+    if samples.is_empty() {
+        return fields;
+    }
+
+    let mut offsets = vec![0usize; samples.len()];
+    // Length of the current run of bytes not part of a string.
+    let mut run = 0usize;
+
+    loop {
+
+        let ended = samples.iter().zip(&offsets)
+            .filter(|&(sample, &offset)| offset >= sample.len())
+            .count();
+
+        if ended != 0 {
+            push_ints(&mut fields, samples, &offsets, run);
+            if ended != samples.len() {
+                fields.push(InferredField::Remaining);
+            }
+            break;
+        }
+
+        if let Some(ends) = try_string(samples, &offsets) {
+            push_ints(&mut fields, samples, &offsets, run);
+            run = 0;
+            fields.push(InferredField::String);
+            offsets = ends;
+        } else if let Some(records) = try_array(samples, &offsets) {
+            push_ints(&mut fields, samples, &offsets, run);
+            fields.push(InferredField::Array { fields: infer_layout(&records) });
+            break;
+        } else {
+            run += 1;
+            offsets.iter_mut().for_each(|offset| *offset += 1);
+        }
+
+    }
+
+    fields
+
+}
+
+/// Internal function to check if all samples have a string at their offset,
+/// returning the offsets after the strings if so.
+fn try_string(samples: &[Vec<u8>], offsets: &[usize]) -> Option<Vec<usize>> {
+
+    let mut ends = Vec::with_capacity(samples.len());
+    let mut max_len = 0;
+
+    for (sample, &offset) in samples.iter().zip(offsets) {
+        let mut data = &sample[offset..];
+        let len = data.read_packed_u32().ok()? as usize;
+        let s = std::str::from_utf8(data.get(..len)?).ok()?;
+        if s.chars().any(char::is_control) {
+            return None;
+        }
+        max_len = max_len.max(len);
+        ends.push(sample.len() - data.len() + len);
+    }
+
+    (max_len >= MIN_STRING_LEN).then_some(ends)
+
+}
+
+/// Internal function to check if all samples have an array at their offset,
+/// with a count prefix followed by records of the same size until the end,
+/// returning all the records if so. At least one sample must have multiple
+/// records, so that single fields are not detected as arrays.
+fn try_array(samples: &[Vec<u8>], offsets: &[usize]) -> Option<Vec<Vec<u8>>> {
+
+    let mut arrays = Vec::with_capacity(samples.len());
+    let mut record_len = None;
+    let mut max_count = 0;
+
+    for (sample, &offset) in samples.iter().zip(offsets) {
+        let mut data = &sample[offset..];
+        let count = data.read_packed_u32().ok()? as usize;
+        if count == 0 {
+            if !data.is_empty() {
+                return None;
+            }
+        } else if data.len() % count != 0 || *record_len.get_or_insert(data.len() / count) != data.len() / count {
+            return None;
+        }
+        max_count = max_count.max(count);
+        arrays.push(data);
+    }
+
+    let record_len = record_len.filter(|&len| len != 0 && max_count >= 2)?;
+
+    Some(arrays.into_iter()
+        .flat_map(|data| data.chunks_exact(record_len))
+        .map(<[u8]>::to_vec)
+        .collect())
+
+}
+
+/// Internal function to push integer fields covering a run of bytes ending
+/// at the given offsets.
+fn push_ints(fields: &mut Vec<InferredField>, samples: &[Vec<u8>], offsets: &[usize], run: usize) {
+
+    let mut pos = run;
+    while pos != 0 {
+
+        let width = if pos >= 4 { 4 } else if pos >= 2 { 2 } else { 1 };
+
+        let mut values = samples.iter().zip(offsets).map(|(sample, &offset)| {
+            let start = offset - pos;
+            sample[start..start + width].iter().rev().fold(0u32, |acc, &b| (acc << 8) | b as u32)
+        });
+
+        let first = values.next().unwrap();
+        let constant = values.all(|value| value == first).then_some(first);
+
+        fields.push(InferredField::Int { width: width as u8, constant });
+        pos -= width;
+
+    }
+
+}
+
+
+/// Write a draft Rust structure for the given inferred layout, followed by
+/// the structures of its arrays' records.
+pub fn write_draft_struct<W: Write>(mut writer: W, name: &str, fields: &[InferredField], sample_count: usize) -> io::Result<()> {
+    writeln!(writer, "/// Draft inferred from {sample_count} samples.")?;
+    write_struct(&mut writer, name, fields)
+}
+
+/// Internal function to write a draft structure and the structures of its
+/// arrays' records, which are named after the structure and field.
+fn write_struct<W: Write>(writer: &mut W, name: &str, fields: &[InferredField]) -> io::Result<()> {
+
+    writeln!(writer, "#[derive(Debug, Clone)]")?;
+    writeln!(writer, "pub struct {name} {{")?;
+
+    for (index, field) in fields.iter().enumerate() {
+        match *field {
+            InferredField::Int { width, constant } => {
+                let ty = match width {
+                    1 => "u8",
+                    2 => "u16",
+                    _ => "u32",
+                };
+                match constant {
+                    Some(value) => writeln!(writer, "    pub field{index}: {ty}, // always 0x{value:0w$X}", w = width as usize * 2)?,
+                    None => writeln!(writer, "    pub field{index}: {ty},")?,
+                }
+            }
+            InferredField::String => writeln!(writer, "    pub field{index}: String,")?,
+            InferredField::Array { .. } => writeln!(writer, "    pub field{index}: Vec<{name}Field{index}>,")?,
+            InferredField::Remaining => writeln!(writer, "    pub remaining: Vec<u8>,")?,
+        }
+    }
+
+    writeln!(writer, "}}")?;
+
+    for (index, field) in fields.iter().enumerate() {
+        if let InferredField::Array { fields } = field {
+            writeln!(writer)?;
+            writeln!(writer, "/// Record of `{name}::field{index}`.")?;
+            write_struct(writer, &format!("{name}Field{index}"), fields)?;
+        }
+    }
+
+    Ok(())
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn infer_fixed_prefix_and_string() {
+
+        let samples = vec![
+            b"\x78\x56\x34\x12\x2A\x00\x05hello\x01".to_vec(),
+            b"\x78\x56\x34\x12\x2A\x00\x07worlds!\x02".to_vec(),
+        ];
+
+        let fields = infer_layout(&samples);
+        assert_eq!(fields, [
+            InferredField::Int { width: 4, constant: Some(0x12345678) },
+            InferredField::Int { width: 2, constant: Some(0x2A) },
+            InferredField::String,
+            InferredField::Int { width: 1, constant: None },
+        ]);
+
+        let mut draft = Vec::new();
+        write_draft_struct(&mut draft, "Test", &fields, samples.len()).unwrap();
+        assert_eq!(String::from_utf8(draft).unwrap(), "\
+/// Draft inferred from 2 samples.
+#[derive(Debug, Clone)]
+pub struct Test {
+    pub field0: u32, // always 0x12345678
+    pub field1: u16, // always 0x002A
+    pub field2: String,
+    pub field3: u8,
+}
+");
+
+    }
+
+    #[test]
+    fn infer_repeated_record() {
+
+        let samples = vec![
+            b"\x01\x00\x03\x10\x00\x01\x11\x00\x00\x12\x00\x01".to_vec(),
+            b"\x01\x00\x02\x20\x00\x00\x21\x00\x01".to_vec(),
+        ];
+
+        let fields = infer_layout(&samples);
+        assert_eq!(fields, [
+            InferredField::Int { width: 2, constant: Some(1) },
+            InferredField::Array { fields: vec![
+                InferredField::Int { width: 2, constant: None },
+                InferredField::Int { width: 1, constant: None },
+            ] },
+        ]);
+
+        let mut draft = Vec::new();
+        write_draft_struct(&mut draft, "Test", &fields, samples.len()).unwrap();
+        assert_eq!(String::from_utf8(draft).unwrap(), "\
+/// Draft inferred from 2 samples.
+#[derive(Debug, Clone)]
+pub struct Test {
+    pub field0: u16, // always 0x0001
+    pub field1: Vec<TestField1>,
+}
+
+/// Record of `Test::field1`.
+#[derive(Debug, Clone)]
+pub struct TestField1 {
+    pub field0: u16,
+    pub field1: u8,
+}
+");
+
+    }
+
+    #[test]
+    fn infer_no_array_from_single_record() {
+        // A count of one is not enough to tell an array from other fields.
+        let samples = vec![b"\x01\x10\x00\x01".to_vec()];
+        assert!(!infer_layout(&samples).iter().any(|field| matches!(field, InferredField::Array { .. })));
+    }
+
+}
@@ -0,0 +1,181 @@
+//! Key log files, recording the session keys negotiated with peers so that
+//! external tools (such as generated dissectors) can decrypt the matching
+//! captures, similarly to `SSLKEYLOGFILE`.
+//!
+//! Each line of the file is an entry of the form `BLOWFISH <label> <key>`,
+//! where the label identifies the session (username or peer address, it
+//! can't contain whitespaces, `-` if unknown) and the key is hex-encoded.
+//! Empty lines and lines starting with `#` are ignored.
+
+use std::io::{self, BufRead, Write};
+use std::fs::{File, OpenOptions};
+use std::fmt::Write as _;
+
+use thiserror::Error;
+
+
+/// Environment variable giving the path of the key log file to append to,
+/// see [`KeyLogWriter::from_env`].
+pub const KEYLOG_ENV: &str = "WGTK_KEYLOGFILE";
+
+/// Tag of Blowfish keys' entries.
+const BLOWFISH_TAG: &str = "BLOWFISH";
+
+
+/// A session key read from a key log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyLogEntry {
+    /// Label identifying the session.
+    pub label: String,
+    /// The Blowfish key of the session.
+    pub blowfish_key: Vec<u8>,
+}
+
+
+/// A writer for key log files, each entry is flushed immediately so that
+/// the file can be read while the session is running.
+pub struct KeyLogWriter<W> {
+    inner: W,
+}
+
+impl KeyLogWriter<File> {
+
+    /// Open the key log file given by the [`KEYLOG_ENV`] environment
+    /// variable in append mode, none is returned if the variable is not set.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        match std::env::var_os(KEYLOG_ENV) {
+            Some(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                Ok(Some(Self::new(file)))
+            }
+            None => Ok(None)
+        }
+    }
+
+}
+
+impl<W: Write> KeyLogWriter<W> {
+
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Write the Blowfish key of a session, whitespaces in the label are
+    /// replaced by underscores and an empty label is written as `-`.
+    pub fn write_blowfish_key(&mut self, label: &str, key: &[u8]) -> io::Result<()> {
+
+        let label = match label {
+            "" => "-".to_string(),
+            label => label.replace(char::is_whitespace, "_"),
+        };
+        let mut key_hex = String::with_capacity(key.len() * 2);
+        for byte in key {
+            write!(key_hex, "{byte:02x}").unwrap();
+        }
+
+        writeln!(self.inner, "{BLOWFISH_TAG} {label} {key_hex}")?;
+        self.inner.flush()
+
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+}
+
+
+/// Read all entries of a key log file.
+pub fn read_keylog<R: BufRead>(reader: R) -> Result<Vec<KeyLogEntry>, KeyLogError> {
+
+    let mut entries = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (Some(BLOWFISH_TAG), Some(label), Some(key_hex), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(KeyLogError::InvalidLine(index + 1));
+        };
+
+        entries.push(KeyLogEntry {
+            label: label.to_string(),
+            blowfish_key: decode_hex(key_hex).ok_or(KeyLogError::InvalidLine(index + 1))?,
+        });
+
+    }
+
+    Ok(entries)
+
+}
+
+/// Internal function to decode a hex string.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes().chunks(2)
+        .map(|byte| match byte {
+            &[high, low] => Some((hex_digit(high)? << 4) | hex_digit(low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Internal function to decode a single hex digit.
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|n| n as u8)
+}
+
+
+/// Errors that can happen while reading a key log file.
+#[derive(Debug, Error)]
+pub enum KeyLogError {
+    /// A line is not a valid entry, the line number starts at 1.
+    #[error("invalid entry at line {0}")]
+    InvalidLine(usize),
+    /// IO error while reading.
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn write_read() {
+
+        let mut writer = KeyLogWriter::new(Vec::new());
+        writer.write_blowfish_key("user name", &[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        writer.write_blowfish_key("", &[0x01, 0x23]).unwrap();
+        let data = writer.into_inner();
+        assert_eq!(data, b"BLOWFISH user_name deadbeef\nBLOWFISH - 0123\n");
+
+        let mut file = b"# comment\n\n".to_vec();
+        file.extend_from_slice(&data);
+        let entries = read_keylog(&file[..]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].label, "user_name");
+        assert_eq!(entries[0].blowfish_key, [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(entries[1].label, "-");
+        assert_eq!(entries[1].blowfish_key, [0x01, 0x23]);
+
+    }
+
+    #[test]
+    fn read_invalid() {
+        assert!(matches!(read_keylog(&b"# comment\nBLOWFISH user 0g\n"[..]), Err(KeyLogError::InvalidLine(2))));
+        assert!(matches!(read_keylog(&b"BLOWFISH user 012\n"[..]), Err(KeyLogError::InvalidLine(1))));
+        assert!(matches!(read_keylog(&b"BLOWFISH  0123\n"[..]), Err(KeyLogError::InvalidLine(1))));
+        assert!(matches!(read_keylog(&b"RSA user 0123\n"[..]), Err(KeyLogError::InvalidLine(1))));
+    }
+
+}
@@ -0,0 +1,153 @@
+//! Entity mailboxes, used to address an entity's component on a given app.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+
+/// Component of an entity addressed by a mailbox, it is encoded in the
+/// 3 most significant bits of the address salt. Values are the same as
+/// BigWorld's `EntityMailBoxRef::Component`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MailboxComponent {
+    Cell = 0,
+    Base = 1,
+    Client = 2,
+    BaseViaCell = 3,
+    ClientViaCell = 4,
+    CellViaBase = 5,
+    ClientViaBase = 6,
+    Service = 7,
+}
+
+impl MailboxComponent {
+
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        Some(match raw {
+            0 => Self::Cell,
+            1 => Self::Base,
+            2 => Self::Client,
+            3 => Self::BaseViaCell,
+            4 => Self::ClientViaCell,
+            5 => Self::CellViaBase,
+            6 => Self::ClientViaBase,
+            7 => Self::Service,
+            _ => return None
+        })
+    }
+
+    /// Return true if this component is the client of the entity.
+    #[inline]
+    pub fn is_client(self) -> bool {
+        matches!(self, Self::Client | Self::ClientViaCell | Self::ClientViaBase)
+    }
+
+}
+
+
+/// A mailbox, referencing an entity's component on the app at the given
+/// address. This is the `MAILBOX` data type on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mailbox {
+    /// The entity's identifier.
+    pub entity_id: i32,
+    /// Address of the app hosting the addressed component.
+    pub addr: SocketAddrV4,
+    /// Salt of the address, only the 13 least significant bits are used.
+    pub salt: u16,
+    /// The addressed component.
+    pub component: MailboxComponent,
+}
+
+impl Mailbox {
+
+    /// Length of a mailbox on the wire.
+    pub const LEN: usize = 12;
+
+    pub fn new(entity_id: i32, addr: SocketAddrV4, component: MailboxComponent) -> Self {
+        Self { entity_id, addr, salt: 0, component }
+    }
+
+    /// Return the same mailbox but addressing another component, the
+    /// address should be changed accordingly if the component is hosted by
+    /// another app.
+    pub fn with_component(self, component: MailboxComponent) -> Self {
+        Self { component, ..self }
+    }
+
+    /// Read a mailbox from its wire representation.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+
+        let entity_id = reader.read_i32::<LE>()?;
+        // IP and port are stored in network order.
+        let ip = reader.read_u32::<LE>()?;
+        let port = reader.read_u16::<LE>()?;
+        let salt = reader.read_u16::<LE>()?;
+
+        let component = MailboxComponent::from_raw((salt >> 13) as u8)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid mailbox component"))?;
+
+        Ok(Self {
+            entity_id,
+            addr: SocketAddrV4::new(Ipv4Addr::from(u32::from_be(ip)), u16::from_be(port)),
+            salt: salt & 0x1FFF,
+            component,
+        })
+
+    }
+
+    /// Write the wire representation of this mailbox.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_i32::<LE>(self.entity_id)?;
+        writer.write_u32::<LE>(u32::from(*self.addr.ip()).to_be())?;
+        writer.write_u16::<LE>(self.addr.port().to_be())?;
+        writer.write_u16::<LE>((self.salt & 0x1FFF) | ((self.component as u16) << 13))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Wire bytes of mailboxes of entity 0x01020304 on 10.0.0.1:20014, the
+    /// last two bytes are the little endian salt with the component in its
+    /// 3 most significant bits.
+    fn wire_mailbox(salt: [u8; 2]) -> [u8; Mailbox::LEN] {
+        [0x04, 0x03, 0x02, 0x01, 0x0A, 0x00, 0x00, 0x01, 0x4E, 0x2E, salt[0], salt[1]]
+    }
+
+    #[test]
+    fn mailbox_read_wire() {
+
+        let components = [
+            (0x00, MailboxComponent::Cell),
+            (0x20, MailboxComponent::Base),
+            (0x40, MailboxComponent::Client),
+            (0x60, MailboxComponent::BaseViaCell),
+            (0x80, MailboxComponent::ClientViaCell),
+            (0xA0, MailboxComponent::CellViaBase),
+            (0xC0, MailboxComponent::ClientViaBase),
+            (0xE0, MailboxComponent::Service),
+        ];
+
+        for (salt_high, component) in components {
+            let data = wire_mailbox([0x23, salt_high | 0x01]);
+            let mailbox = Mailbox::read(&data[..]).unwrap();
+            assert_eq!(mailbox.entity_id, 0x01020304);
+            assert_eq!(mailbox.addr, SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 20014));
+            assert_eq!(mailbox.salt, 0x0123);
+            assert_eq!(mailbox.component, component);
+            let mut written = Vec::new();
+            mailbox.write(&mut written).unwrap();
+            assert_eq!(written, data);
+        }
+
+    }
+
+}
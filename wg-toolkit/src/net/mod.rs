@@ -3,25 +3,212 @@
 pub mod packet;
 pub mod element;
 pub mod bundle;
+pub mod pool;
+pub mod mailbox;
+pub mod capture;
+pub mod keylog;
+pub mod dissector;
+pub mod diff;
+pub mod corpus;
+pub mod infer;
 // pub mod interface;
 pub mod proxy;
 pub mod filter;
+pub mod crypto;
+pub mod policy;
 
 
-/// Packet's flags.
+/// Packet's flags, unknown bits are kept by [`PacketFlags::from_bits`], but
+/// packets with flags unknown to this crate can't be decoded.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(transparent)]
-pub struct PacketFlags(());
+pub struct PacketFlags(u16);
 
 impl PacketFlags {
-    const HAS_REQUESTS: u16        = 0x0001;
-    const HAS_PIGGYBACKS: u16      = 0x0002;
-    const HAS_ACKS: u16            = 0x0004;
-    const ON_CHANNEL: u16          = 0x0008;
-    const IS_RELIABLE: u16         = 0x0010;
-    const IS_FRAGMENT: u16         = 0x0020;
-    const HAS_SEQUENCE_NUMBER: u16 = 0x0040;
-    const INDEXED_CHANNEL: u16     = 0x0080;
-    const HAS_CHECKSUM: u16        = 0x0100;
-    const CREATE_CHANNEL: u16      = 0x0200;
-    const HAS_CUMULATIVE_ACK: u16  = 0x0400;
+
+    pub const HAS_REQUESTS: Self        = Self(0x0001);
+    pub const HAS_PIGGYBACKS: Self      = Self(0x0002);
+    pub const HAS_ACKS: Self            = Self(0x0004);
+    pub const ON_CHANNEL: Self          = Self(0x0008);
+    pub const IS_RELIABLE: Self         = Self(0x0010);
+    pub const IS_FRAGMENT: Self         = Self(0x0020);
+    pub const HAS_SEQUENCE_NUMBER: Self = Self(0x0040);
+    pub const INDEXED_CHANNEL: Self     = Self(0x0080);
+    pub const HAS_CHECKSUM: Self        = Self(0x0100);
+    pub const CREATE_CHANNEL: Self      = Self(0x0200);
+    pub const HAS_CUMULATIVE_ACK: Self  = Self(0x0400);
+
+    /// All flags with their name, in the order of their bits.
+    pub const ALL: [(Self, &'static str); 11] = [
+        (Self::HAS_REQUESTS, "HAS_REQUESTS"),
+        (Self::HAS_PIGGYBACKS, "HAS_PIGGYBACKS"),
+        (Self::HAS_ACKS, "HAS_ACKS"),
+        (Self::ON_CHANNEL, "ON_CHANNEL"),
+        (Self::IS_RELIABLE, "IS_RELIABLE"),
+        (Self::IS_FRAGMENT, "IS_FRAGMENT"),
+        (Self::HAS_SEQUENCE_NUMBER, "HAS_SEQUENCE_NUMBER"),
+        (Self::INDEXED_CHANNEL, "INDEXED_CHANNEL"),
+        (Self::HAS_CHECKSUM, "HAS_CHECKSUM"),
+        (Self::CREATE_CHANNEL, "CREATE_CHANNEL"),
+        (Self::HAS_CUMULATIVE_ACK, "HAS_CUMULATIVE_ACK"),
+    ];
+
+    #[inline]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Create flags from their raw bits, unknown bits are kept.
+    #[inline]
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Return true if all the given flags are set.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Return true if any of the given flags is set.
+    #[inline]
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    #[inline]
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// Insert or remove the given flags.
+    #[inline]
+    pub fn set(&mut self, other: Self, enabled: bool) {
+        if enabled {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
+    }
+
+    /// Return the flags that are set in self or in other.
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Return the flags that are set in self but not in other.
+    #[inline]
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+}
+
+impl std::ops::BitOr for PacketFlags {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl std::ops::BitOrAssign for PacketFlags {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.insert(rhs);
+    }
+}
+
+impl std::ops::BitAnd for PacketFlags {
+    type Output = Self;
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl std::fmt::Debug for PacketFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PacketFlags(")?;
+        let mut remaining = *self;
+        let mut first = true;
+        for (flag, name) in Self::ALL {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                remaining.remove(flag);
+                first = false;
+            }
+        }
+        if !remaining.is_empty() || first {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            write!(f, "0x{:04X}", remaining.0)?;
+        }
+        f.write_str(")")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn packet_flags_ops() {
+
+        let mut flags = PacketFlags::HAS_REQUESTS | PacketFlags::HAS_CHECKSUM;
+        assert_eq!(flags.bits(), 0x0101);
+        assert!(flags.contains(PacketFlags::HAS_REQUESTS));
+        assert!(!flags.contains(PacketFlags::HAS_REQUESTS | PacketFlags::IS_RELIABLE));
+        assert!(flags.intersects(PacketFlags::HAS_REQUESTS | PacketFlags::IS_RELIABLE));
+        assert!(!flags.intersects(PacketFlags::IS_RELIABLE));
+
+        flags.insert(PacketFlags::IS_RELIABLE);
+        assert_eq!(flags.bits(), 0x0111);
+        flags.remove(PacketFlags::HAS_CHECKSUM);
+        assert_eq!(flags.bits(), 0x0011);
+        flags.set(PacketFlags::HAS_CHECKSUM, true);
+        flags.set(PacketFlags::HAS_REQUESTS, false);
+        assert_eq!(flags, PacketFlags::IS_RELIABLE | PacketFlags::HAS_CHECKSUM);
+
+        assert_eq!(flags.difference(PacketFlags::IS_RELIABLE), PacketFlags::HAS_CHECKSUM);
+        assert_eq!(flags & PacketFlags::IS_RELIABLE, PacketFlags::IS_RELIABLE);
+        flags |= PacketFlags::ON_CHANNEL;
+        assert_eq!(flags.bits(), 0x0118);
+
+        assert!(PacketFlags::empty().is_empty());
+        assert_eq!(PacketFlags::from_bits(0x8001).bits(), 0x8001);
+
+    }
+
+    #[test]
+    fn packet_flags_debug() {
+        assert_eq!(format!("{:?}", PacketFlags::empty()), "PacketFlags(0x0000)");
+        assert_eq!(format!("{:?}", PacketFlags::HAS_REQUESTS | PacketFlags::HAS_CHECKSUM), "PacketFlags(HAS_REQUESTS | HAS_CHECKSUM)");
+        assert_eq!(format!("{:?}", PacketFlags::from_bits(0x8040)), "PacketFlags(HAS_SEQUENCE_NUMBER | 0x8000)");
+        assert_eq!(format!("{:?}", PacketFlags::from_bits(0x8000)), "PacketFlags(0x8000)");
+    }
+
 }
@@ -23,6 +23,12 @@ pub const PACKET_MAX_BODY_LEN: usize =
     PACKET_FLAGS_LEN -
     PACKET_PREFIX_LEN;
 
+/// Flags that don't add any footer, they are decoded and kept when the
+/// packet's data is synchronized again.
+const CHANNEL_FLAGS: PacketFlags = PacketFlags::ON_CHANNEL
+    .union(PacketFlags::IS_RELIABLE)
+    .union(PacketFlags::CREATE_CHANNEL);
+
 
 pub struct Packet {
     /// Raw data of the packet, header and footer data is not valid until
@@ -52,6 +58,8 @@ pub struct Packet {
     seq: u32,
     /// Enable or disable checksum.
     has_checksum: bool,
+    /// Flags of the packet, only valid after synchronization.
+    flags: PacketFlags,
 }
 
 impl Packet {
@@ -67,6 +75,7 @@ impl Packet {
             seq_last: 0,
             seq: 0,
             has_checksum: false,
+            flags: PacketFlags::empty(),
         }
     }
 
@@ -74,6 +83,16 @@ impl Packet {
         Box::new(Self::new(has_prefix))
     }
 
+    /// Reset this packet to the same state as a new packet, without clearing
+    /// its raw data. This is used to reuse pooled packets.
+    pub fn reset(&mut self, has_prefix: bool) {
+        self.prefix = if has_prefix { Some(0) } else { None };
+        self.has_checksum = false;
+        self.flags = PacketFlags::empty();
+        self.seq = 0;
+        self.clear();
+    }
+
     // Prefix
 
     /// Returns true if the first 4 bytes are used.
@@ -100,10 +119,11 @@ impl Packet {
         self.len
     }
 
-    /// Return the raw length of this packet, including reserved first 4 bytes.
+    /// Return the raw length of this packet, including the prefix if present.
+    /// This is the length of the slice returned by `get_raw_data`.
     #[inline]
     pub fn raw_len(&self) -> usize {
-        self.len + PACKET_PREFIX_LEN
+        self.len + if self.has_prefix() { PACKET_PREFIX_LEN } else { 0 }
     }
 
     /// Return the free length available in this packet.
@@ -264,6 +284,39 @@ impl Packet {
         (self.seq_first, self.seq_last, self.seq)
     }
 
+    // Flags
+
+    /// Return the flags of this packet, as decoded by `sync_state` or
+    /// encoded by `sync_data`.
+    #[inline]
+    pub fn get_flags(&self) -> PacketFlags {
+        self.flags
+    }
+
+    /// Returns `true` if this packet is a fragment of a bundle.
+    #[inline]
+    pub fn is_fragment(&self) -> bool {
+        self.flags.contains(PacketFlags::IS_FRAGMENT)
+    }
+
+    /// Returns `true` if this packet is sent on a channel.
+    #[inline]
+    pub fn is_on_channel(&self) -> bool {
+        self.flags.contains(PacketFlags::ON_CHANNEL)
+    }
+
+    /// Returns `true` if this packet creates a channel.
+    #[inline]
+    pub fn is_create_channel(&self) -> bool {
+        self.flags.contains(PacketFlags::CREATE_CHANNEL)
+    }
+
+    /// Returns `true` if this packet is reliable.
+    #[inline]
+    pub fn is_reliable(&self) -> bool {
+        self.flags.contains(PacketFlags::IS_RELIABLE)
+    }
+
     // Checksum
 
     pub fn has_checksum(&self) -> bool {
@@ -310,11 +363,10 @@ impl Packet {
         // Go to the end of the packet.
         cursor.set_position((PACKET_PREFIX_LEN + self.len) as u64);
 
-        let mut flags = 0u16;
+        let mut flags = self.flags & CHANNEL_FLAGS;
 
         if has_seq {
-            flags |= PacketFlags::IS_FRAGMENT;
-            flags |= PacketFlags::HAS_SEQUENCE_NUMBER;
+            flags |= PacketFlags::IS_FRAGMENT | PacketFlags::HAS_SEQUENCE_NUMBER;
             cursor.write_u32::<LittleEndian>(self.seq_first).unwrap();
             cursor.write_u32::<LittleEndian>(self.seq_last).unwrap();
         }
@@ -341,13 +393,15 @@ impl Packet {
 
         // Finally, write flags.
         cursor.set_position(PACKET_PREFIX_LEN as u64);
-        cursor.write_u16::<LittleEndian>(flags).unwrap();
+        cursor.write_u16::<LittleEndian>(flags.bits()).unwrap();
+        self.flags = flags;
 
         // Calculate checksum and write it if enabled.
         // Placed here to take flags into checksum.
         if self.has_checksum {
             cursor.set_position(PACKET_PREFIX_LEN as u64);
             let checksum = Self::calc_checksum(&mut cursor, self.len as u64);
+            cursor.set_position((PACKET_PREFIX_LEN + self.len) as u64);
             cursor.write_u32::<LittleEndian>(checksum).unwrap();
             self.len += 4;
         }
@@ -361,8 +415,14 @@ impl Packet {
     /// *If this function returns an error, the integrity of the internal state is not guaranteed.*
     pub fn sync_state(&mut self, len: usize/*, has_prefix: bool*/) -> Result<(), PacketSyncError> {
 
-        // Fix length if it contains a 4-bytes prefix.
-        let real_len = len - if self.has_prefix() { PACKET_PREFIX_LEN } else { 0 };
+        if len > self.get_raw_data().len() {
+            return Err(PacketSyncError::TooLong);
+        }
+
+        // Fix length if it contains a 4-bytes prefix, the flags must be present.
+        let real_len = len.checked_sub(if self.has_prefix() { PACKET_PREFIX_LEN } else { 0 })
+            .filter(|&real_len| real_len >= PACKET_FLAGS_LEN)
+            .ok_or(PacketSyncError::TooShort)?;
 
         let mut cursor = Cursor::new(&mut self.data[..]);
 
@@ -373,23 +433,41 @@ impl Packet {
             cursor.set_position(PACKET_PREFIX_LEN as u64);
         }
 
-        let flags = cursor.read_u16::<LittleEndian>().unwrap();
+        let flags = PacketFlags::from_bits(cursor.read_u16::<LittleEndian>().unwrap());
+        self.flags = flags;
+
+        // The checksum is always the last field of the packet, it's checked
+        // first so that other flags and footers of corrupted packets are not
+        // interpreted.
+        self.has_checksum = flags.contains(PacketFlags::HAS_CHECKSUM);
+        if self.has_checksum {
+            if real_len < PACKET_FLAGS_LEN + 4 {
+                return Err(PacketSyncError::TooShort);
+            }
+            let checksum_offset = real_len - 4;
+            cursor.set_position((PACKET_PREFIX_LEN + checksum_offset) as u64);
+            let checksum = cursor.read_u32::<LittleEndian>().unwrap();
+            cursor.set_position(PACKET_PREFIX_LEN as u64);
+            if checksum != Self::calc_checksum(&mut cursor, checksum_offset as u64) {
+                return Err(PacketSyncError::InvalidChecksum);
+            }
+        }
 
-        const KNOWN_FLAGS: u16 =
-            PacketFlags::HAS_CHECKSUM |
-            PacketFlags::HAS_SEQUENCE_NUMBER |
-            PacketFlags::HAS_REQUESTS |
-            PacketFlags::IS_FRAGMENT;
+        const KNOWN_FLAGS: PacketFlags = PacketFlags::HAS_CHECKSUM
+            .union(PacketFlags::HAS_SEQUENCE_NUMBER)
+            .union(PacketFlags::HAS_REQUESTS)
+            .union(PacketFlags::IS_FRAGMENT)
+            .union(CHANNEL_FLAGS);
 
-        if flags & !KNOWN_FLAGS != 0 {
-            return Err(PacketSyncError::UnknownFlags(flags & !KNOWN_FLAGS));
+        let unknown_flags = flags.difference(KNOWN_FLAGS);
+        if !unknown_flags.is_empty() {
+            return Err(PacketSyncError::UnknownFlags(unknown_flags.bits()));
         }
 
-        self.has_checksum = flags & PacketFlags::HAS_CHECKSUM != 0;
-        let has_seq = flags & PacketFlags::HAS_SEQUENCE_NUMBER != 0;
-        let has_requests = flags & PacketFlags::HAS_REQUESTS != 0;
+        let has_seq = flags.contains(PacketFlags::HAS_SEQUENCE_NUMBER);
+        let has_requests = flags.contains(PacketFlags::HAS_REQUESTS);
 
-        if has_seq && flags & PacketFlags::IS_FRAGMENT == 0 {
+        if has_seq && !flags.contains(PacketFlags::IS_FRAGMENT) {
             return Err(PacketSyncError::MissingFragmentFlag);
         }
 
@@ -428,18 +506,12 @@ impl Packet {
 
         // TODO: Acks
 
+        // Checksum has already been checked.
         if self.has_checksum {
-            let pos = cursor.position();
-            let checksum = cursor.read_u32::<LittleEndian>().unwrap();
-            cursor.set_position(PACKET_PREFIX_LEN as u64);
-            let real_checksum = Self::calc_checksum(&mut cursor, pos);
-            if checksum != real_checksum {
-                return Err(PacketSyncError::InvalidChecksum);
-            }
-            cursor.set_position(pos + 4);
+            cursor.set_position(cursor.position() + 4);
         }
 
-        debug_assert_eq!(cursor.position(), len as u64, "Wrong calculated footer size.");
+        debug_assert_eq!(cursor.position(), (PACKET_PREFIX_LEN + real_len) as u64, "Wrong calculated footer size.");
         Ok(())
 
     }
@@ -492,6 +564,137 @@ pub enum PacketSyncError {
     MissingFragmentFlag,
     /// Not enough length available to decode this packet's footers correctly.
     TooShort,
+    /// The given length is greater than the packet's capacity.
+    TooLong,
     /// The packet has checksum and the calculated checksum doesn't correspond.
     InvalidChecksum
 }
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn sync_too_short() {
+
+        for len in 0..PACKET_PREFIX_LEN + PACKET_FLAGS_LEN {
+            let mut packet = Packet::new_boxed(true);
+            assert!(matches!(packet.sync_state(len), Err(PacketSyncError::TooShort)), "len {len}");
+        }
+
+        for len in 0..PACKET_FLAGS_LEN {
+            let mut packet = Packet::new_boxed(false);
+            assert!(matches!(packet.sync_state(len), Err(PacketSyncError::TooShort)), "len {len}");
+        }
+
+        let mut packet = Packet::new_boxed(true);
+        assert!(packet.sync_state(PACKET_PREFIX_LEN + PACKET_FLAGS_LEN).is_ok());
+
+    }
+
+    /// Create a packet with a checksum and the given body, then synchronize
+    /// its data and return its raw data, including the checksum.
+    fn checksum_packet_data(body: &[u8]) -> Vec<u8> {
+        let mut packet = Packet::new_boxed(false);
+        packet.set_checksum(true);
+        packet.reserve_unchecked(body.len()).copy_from_slice(body);
+        packet.sync_data();
+        packet.get_raw_data()[..packet.raw_len()].to_vec()
+    }
+
+    /// Copy the given raw data into a new packet and synchronize its state.
+    fn sync_packet_data(data: &[u8]) -> (Box<Packet>, Result<(), PacketSyncError>) {
+        let mut packet = Packet::new_boxed(false);
+        packet.get_raw_data_mut()[..data.len()].copy_from_slice(data);
+        let res = packet.sync_state(data.len());
+        (packet, res)
+    }
+
+    #[test]
+    fn sync_checksum_valid() {
+        let data = checksum_packet_data(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let (packet, res) = sync_packet_data(&data);
+        assert!(res.is_ok());
+        assert!(packet.has_checksum());
+        assert_eq!(packet.get_body_data(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn sync_checksum_invalid() {
+        let mut data = checksum_packet_data(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[PACKET_FLAGS_LEN] ^= 0xFF;
+        let (_, res) = sync_packet_data(&data);
+        assert!(matches!(res, Err(PacketSyncError::InvalidChecksum)));
+    }
+
+    #[test]
+    fn sync_checksum_too_short() {
+        let flags = PacketFlags::HAS_CHECKSUM.bits().to_le_bytes();
+        for data in [&flags[..], &[flags[0], flags[1], 0, 0][..]] {
+            let (_, res) = sync_packet_data(data);
+            assert!(matches!(res, Err(PacketSyncError::TooShort)), "len {}", data.len());
+        }
+    }
+
+    #[test]
+    fn sync_channel_flags() {
+
+        let flags = PacketFlags::ON_CHANNEL | PacketFlags::IS_RELIABLE | PacketFlags::CREATE_CHANNEL;
+        let (mut packet, res) = sync_packet_data(&flags.bits().to_le_bytes());
+        assert!(res.is_ok());
+        assert_eq!(packet.get_flags(), flags);
+        assert!(packet.is_on_channel());
+        assert!(packet.is_reliable());
+        assert!(packet.is_create_channel());
+        assert!(!packet.is_fragment());
+
+        // These flags are kept when encoding the packet again.
+        packet.sync_data();
+        assert_eq!(packet.get_flags(), flags);
+        assert_eq!(packet.get_data(), flags.bits().to_le_bytes());
+
+        let (packet, res) = sync_packet_data(&PacketFlags::IS_RELIABLE.bits().to_le_bytes());
+        assert!(res.is_ok());
+        assert!(packet.is_reliable());
+        assert!(!packet.is_on_channel());
+
+    }
+
+    #[test]
+    fn sync_footer_flags() {
+
+        let mut packet = Packet::new_boxed(false);
+        packet.reserve_unchecked(8).copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        packet.set_seq(10, 11, 10);
+        packet.set_request_first_offset(PACKET_FLAGS_LEN);
+        packet.sync_data();
+        let data = packet.get_raw_data()[..packet.raw_len()].to_vec();
+
+        let (packet, res) = sync_packet_data(&data);
+        assert!(res.is_ok());
+        assert!(packet.is_fragment());
+        assert!(packet.get_flags().contains(PacketFlags::HAS_SEQUENCE_NUMBER | PacketFlags::HAS_REQUESTS));
+        assert_eq!(packet.get_seq(), (10, 11, 10));
+        assert_eq!(packet.get_request_first_offset(), PACKET_FLAGS_LEN);
+        assert_eq!(packet.get_body_data(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+    }
+
+    #[test]
+    fn sync_unknown_flags() {
+        for flags in [PacketFlags::HAS_ACKS, PacketFlags::INDEXED_CHANNEL, PacketFlags::from_bits(0x8000)] {
+            let (_, res) = sync_packet_data(&(flags | PacketFlags::IS_RELIABLE).bits().to_le_bytes());
+            assert!(matches!(res, Err(PacketSyncError::UnknownFlags(bits)) if bits == flags.bits()), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn sync_too_long() {
+        let mut packet = Packet::new_boxed(false);
+        let len = packet.get_raw_data().len();
+        assert!(matches!(packet.sync_state(len + 1), Err(PacketSyncError::TooLong)));
+    }
+
+}
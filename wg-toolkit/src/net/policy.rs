@@ -0,0 +1,287 @@
+//! Access control of peers, policies are consulted when a datagram is
+//! received from a peer, before any state is created for it.
+
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+
+/// A policy deciding if a peer is allowed to communicate with us.
+///
+/// This trait is implemented for closures, which can be used as an
+/// extension point, for example to check the country of the peer against
+/// a GeoIP database.
+pub trait PeerPolicy {
+
+    /// Check if the given peer is allowed.
+    fn check(&mut self, addr: SocketAddr) -> PeerDecision;
+
+}
+
+impl<F> PeerPolicy for F
+where
+    F: FnMut(SocketAddr) -> PeerDecision,
+{
+    #[inline]
+    fn check(&mut self, addr: SocketAddr) -> PeerDecision {
+        (self)(addr)
+    }
+}
+
+/// The decision of a peer policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerDecision {
+    Allow,
+    Reject(RejectReason),
+}
+
+/// The reason of a peer's rejection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RejectReason {
+    /// The peer matches a deny list.
+    Denied,
+    /// An allow list is defined and the peer doesn't match it.
+    NotAllowed,
+    /// A custom reason given by a user policy.
+    Custom(String),
+}
+
+/// An event emitted when a peer has been rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeerRejection {
+    pub addr: SocketAddr,
+    pub reason: RejectReason,
+}
+
+
+/// A block of IP addresses in CIDR notation, such as `10.0.0.0/8` or
+/// `2001:db8::/32`. A single address is parsed as a block of this only
+/// address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+
+    /// Create a new block from an address and a prefix length, none is
+    /// returned if the prefix is longer than the address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<Self> {
+        (prefix_len <= max_prefix_len(addr)).then_some(Self { addr, prefix_len })
+    }
+
+    #[inline]
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    #[inline]
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Return true if the given address is in this block. IPv4-mapped
+    /// IPv6 addresses are matched against IPv4 blocks.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let addr = match addr {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(addr),
+            addr => addr,
+        };
+        match (self.addr, addr) {
+            (IpAddr::V4(block), IpAddr::V4(addr)) => {
+                prefix_eq(u32::from(block) as u128, u32::from(addr) as u128, 32, self.prefix_len)
+            }
+            (IpAddr::V6(block), IpAddr::V6(addr)) => {
+                prefix_eq(u128::from(block), u128::from(addr), 128, self.prefix_len)
+            }
+            _ => false
+        }
+    }
+
+}
+
+impl FromStr for Cidr {
+
+    type Err = CidrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => {
+                let addr = addr.parse::<IpAddr>().map_err(|_| CidrParseError::InvalidAddr)?;
+                let prefix_len = prefix_len.parse::<u8>().map_err(|_| CidrParseError::InvalidPrefixLen)?;
+                (addr, prefix_len)
+            }
+            None => {
+                let addr = s.parse::<IpAddr>().map_err(|_| CidrParseError::InvalidAddr)?;
+                (addr, max_prefix_len(addr))
+            }
+        };
+        Self::new(addr, prefix_len).ok_or(CidrParseError::InvalidPrefixLen)
+    }
+
+}
+
+/// Internal function to get the maximum prefix length of an address.
+fn max_prefix_len(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Internal function to compare the first bits of two addresses of the
+/// given bit length.
+fn prefix_eq(a: u128, b: u128, bits: u8, prefix_len: u8) -> bool {
+    let shift = (bits - prefix_len) as u32;
+    a.checked_shr(shift).unwrap_or(0) == b.checked_shr(shift).unwrap_or(0)
+}
+
+
+/// A policy with CIDR allow and deny lists. A peer is rejected if it's in
+/// the deny list, or if the allow list is not empty and the peer is not in
+/// it, so an empty policy allows every peer.
+#[derive(Debug, Clone, Default)]
+pub struct CidrPolicy {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl CidrPolicy {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a block to the allow list.
+    pub fn allow(&mut self, cidr: Cidr) {
+        self.allow.push(cidr);
+    }
+
+    /// Add a block to the deny list.
+    pub fn deny(&mut self, cidr: Cidr) {
+        self.deny.push(cidr);
+    }
+
+}
+
+impl PeerPolicy for CidrPolicy {
+
+    fn check(&mut self, addr: SocketAddr) -> PeerDecision {
+        let ip = addr.ip();
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            PeerDecision::Reject(RejectReason::Denied)
+        } else if !self.allow.is_empty() && !self.allow.iter().any(|cidr| cidr.contains(ip)) {
+            PeerDecision::Reject(RejectReason::NotAllowed)
+        } else {
+            PeerDecision::Allow
+        }
+    }
+
+}
+
+
+/// Errors that can happen while parsing a CIDR block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum CidrParseError {
+    #[error("invalid address")]
+    InvalidAddr,
+    #[error("invalid prefix length")]
+    InvalidPrefixLen,
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn check(policy: &mut CidrPolicy, addr: &str) -> PeerDecision {
+        policy.check(SocketAddr::new(ip(addr), 20014))
+    }
+
+    #[test]
+    fn cidr_v4() {
+
+        let any = cidr("0.0.0.0/0");
+        assert!(any.contains(ip("0.0.0.0")));
+        assert!(any.contains(ip("255.255.255.255")));
+        assert!(!any.contains(ip("::1")));
+
+        let single = cidr("192.168.1.10/32");
+        assert!(single.contains(ip("192.168.1.10")));
+        assert!(!single.contains(ip("192.168.1.11")));
+        assert_eq!(cidr("192.168.1.10"), single);
+
+        let block = cidr("10.1.0.0/16");
+        assert!(block.contains(ip("10.1.255.3")));
+        assert!(!block.contains(ip("10.2.0.0")));
+
+    }
+
+    #[test]
+    fn cidr_v6() {
+
+        let any = cidr("::/0");
+        assert!(any.contains(ip("2001:db8::1")));
+        assert!(!any.contains(ip("10.0.0.1")));
+
+        let single = cidr("::1/128");
+        assert!(single.contains(ip("::1")));
+        assert!(!single.contains(ip("::2")));
+        assert_eq!(cidr("::1"), single);
+
+        let block = cidr("2001:db8::/32");
+        assert!(block.contains(ip("2001:db8:ffff::1")));
+        assert!(!block.contains(ip("2001:db9::1")));
+
+    }
+
+    #[test]
+    fn cidr_v4_mapped() {
+        let block = cidr("10.0.0.0/8");
+        assert!(block.contains(ip("::ffff:10.2.3.4")));
+        assert!(!block.contains(ip("::ffff:11.2.3.4")));
+    }
+
+    #[test]
+    fn cidr_invalid() {
+        assert_eq!("10.0.0.0/33".parse::<Cidr>(), Err(CidrParseError::InvalidPrefixLen));
+        assert_eq!("::/129".parse::<Cidr>(), Err(CidrParseError::InvalidPrefixLen));
+        assert_eq!("10.0.0.0/".parse::<Cidr>(), Err(CidrParseError::InvalidPrefixLen));
+        assert_eq!("10.0.0.0/-1".parse::<Cidr>(), Err(CidrParseError::InvalidPrefixLen));
+        assert_eq!("10.0.0/8".parse::<Cidr>(), Err(CidrParseError::InvalidAddr));
+        assert_eq!(Cidr::new(ip("10.0.0.0"), 33), None);
+    }
+
+    #[test]
+    fn cidr_policy() {
+
+        let mut policy = CidrPolicy::new();
+        assert_eq!(check(&mut policy, "1.2.3.4"), PeerDecision::Allow);
+
+        policy.allow(cidr("10.0.0.0/8"));
+        policy.allow(cidr("2001:db8::/32"));
+        policy.deny(cidr("10.1.0.0/16"));
+
+        assert_eq!(check(&mut policy, "10.2.0.1"), PeerDecision::Allow);
+        assert_eq!(check(&mut policy, "::ffff:10.2.0.1"), PeerDecision::Allow);
+        assert_eq!(check(&mut policy, "2001:db8::1"), PeerDecision::Allow);
+        assert_eq!(check(&mut policy, "10.1.0.1"), PeerDecision::Reject(RejectReason::Denied));
+        assert_eq!(check(&mut policy, "1.2.3.4"), PeerDecision::Reject(RejectReason::NotAllowed));
+        assert_eq!(check(&mut policy, "::1"), PeerDecision::Reject(RejectReason::NotAllowed));
+
+    }
+
+}
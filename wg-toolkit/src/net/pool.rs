@@ -0,0 +1,158 @@
+//! Pool of reusable packets, used to avoid allocating a packet for each
+//! received datagram or each new packet in a bundle.
+
+use std::sync::{Arc, Mutex};
+
+use super::packet::Packet;
+
+
+/// Default maximum number of free packets kept by a pool.
+pub const PACKET_POOL_DEFAULT_CAPACITY: usize = 256;
+
+
+/// A freelist of boxed packets. This handle is cheap to clone and all clones
+/// share the same freelist, it can be given to bundles and assemblers which
+/// will release their packets to the pool when dropped.
+#[derive(Clone)]
+pub struct PacketPool {
+    inner: Arc<PacketPoolInner>,
+}
+
+struct PacketPoolInner {
+    /// Free packets ready to be acquired, kept boxed because they are given
+    /// as is to bundles.
+    #[allow(clippy::vec_box)]
+    free: Mutex<Vec<Box<Packet>>>,
+    /// Maximum number of free packets to keep.
+    capacity: usize,
+}
+
+impl PacketPool {
+
+    /// Create a new pool keeping at most the given number of free packets,
+    /// packets released to a full pool are just dropped.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(PacketPoolInner {
+                free: Mutex::new(Vec::new()),
+                capacity,
+            })
+        }
+    }
+
+    /// Acquire a packet from the pool, or allocate a new one if the pool is
+    /// empty. The returned packet is in the same state as a new packet.
+    pub fn acquire(&self, has_prefix: bool) -> Box<Packet> {
+        match self.inner.free.lock().unwrap().pop() {
+            Some(mut packet) => {
+                packet.reset(has_prefix);
+                packet
+            }
+            None => Packet::new_boxed(has_prefix)
+        }
+    }
+
+    /// Release a packet to the pool.
+    pub fn release(&self, packet: Box<Packet>) {
+        let mut free = self.inner.free.lock().unwrap();
+        if free.len() < self.inner.capacity {
+            free.push(packet);
+        }
+    }
+
+    /// Release all given packets to the pool.
+    pub fn release_all<I: IntoIterator<Item = Box<Packet>>>(&self, packets: I) {
+        let mut free = self.inner.free.lock().unwrap();
+        let remaining = self.inner.capacity.saturating_sub(free.len());
+        free.extend(packets.into_iter().take(remaining));
+    }
+
+    /// Return the number of free packets currently in the pool.
+    pub fn free_len(&self) -> usize {
+        self.inner.free.lock().unwrap().len()
+    }
+
+}
+
+impl Default for PacketPool {
+    fn default() -> Self {
+        Self::new(PACKET_POOL_DEFAULT_CAPACITY)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::net::bundle::Bundle;
+    use crate::net::element::Var16ElementCodec;
+    use crate::net::PacketFlags;
+
+    #[test]
+    fn acquire_reset() {
+
+        let pool = PacketPool::new(1);
+
+        let mut packet = pool.acquire(true);
+        packet.set_prefix(Some(1234));
+        packet.set_checksum(true);
+        packet.reserve_unchecked(8);
+        packet.set_request_first_offset(2);
+        packet.set_seq(1, 3, 2);
+        packet.sync_data();
+        assert!(!packet.get_flags().is_empty());
+        pool.release(packet);
+
+        // The packet is reused but its state is the same as a new one.
+        let packet = pool.acquire(false);
+        assert_eq!(pool.free_len(), 0);
+        assert_eq!(packet.get_prefix(), None);
+        assert!(!packet.has_checksum());
+        assert_eq!(packet.get_flags(), PacketFlags::empty());
+        assert!(!packet.has_seq());
+        assert_eq!(packet.get_seq(), (0, 0, 0));
+        assert!(!packet.has_requests());
+        assert_eq!(packet.body_len(), 0);
+        assert_eq!(packet.len(), Packet::new(false).len());
+
+        let packet = pool.acquire(true);
+        assert_eq!(packet.get_prefix(), Some(0));
+
+    }
+
+    #[test]
+    fn capacity() {
+
+        let pool = PacketPool::new(2);
+        let packets = (0..3).map(|_| pool.acquire(false)).collect::<Vec<_>>();
+        assert_eq!(pool.free_len(), 0);
+
+        pool.release_all(packets);
+        assert_eq!(pool.free_len(), 2);
+        pool.release(Packet::new_boxed(false));
+        assert_eq!(pool.free_len(), 2);
+
+        let _packet = pool.acquire(false);
+        assert_eq!(pool.free_len(), 1);
+
+    }
+
+    #[test]
+    fn bundle_drop() {
+
+        let pool = PacketPool::default();
+        let codec = Var16ElementCodec::new();
+
+        let mut bundle = Bundle::new_empty(false).with_pool(pool.clone());
+        bundle.add_element(0x10, &codec, vec![0; 2000]);
+        bundle.finalize(&mut 0);
+        assert!(bundle.len() > 1);
+
+        let len = bundle.len();
+        drop(bundle);
+        assert_eq!(pool.free_len(), len);
+
+    }
+
+}
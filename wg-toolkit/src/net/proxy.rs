@@ -11,6 +11,8 @@ use mio::{Events, Interest, Poll, Token};
 
 use crate::net::bundle::Bundle;
 use crate::net::packet::Packet;
+use crate::net::pool::PacketPool;
+use crate::net::policy::{PeerPolicy, PeerDecision, PeerRejection};
 
 
 const CLIENT_AVAIL: Token = Token(0);
@@ -23,7 +25,8 @@ pub struct Proxy<CL, SL> {
     client: ProxySide<ProxyClientHandler, CL>,
     server: ProxySide<ProxyServerHandler, SL>,
     poll: Poll,
-    events: Events
+    events: Events,
+    pool: PacketPool,
 }
 
 impl<CL, SL> Proxy<CL, SL>
@@ -44,8 +47,9 @@ where
         server_listener: SL
     ) -> io::Result<Self> {
 
-        let mut client = ProxySide::new(client_bind_addr, ProxyClientHandler::new(), client_listener)?;
-        let mut server = ProxySide::new(server_bind_addr, ProxyServerHandler::new(server_addr), server_listener)?;
+        let pool = PacketPool::default();
+        let mut client = ProxySide::new(client_bind_addr, ProxyClientHandler::new(), client_listener, pool.clone())?;
+        let mut server = ProxySide::new(server_bind_addr, ProxyServerHandler::new(server_addr), server_listener, pool.clone())?;
 
         let poll = Poll::new()?;
         poll.registry().register(&mut client.sock, CLIENT_AVAIL, Interest::READABLE)?;
@@ -55,11 +59,42 @@ where
             client,
             server,
             poll,
-            events: Events::with_capacity(128)
+            events: Events::with_capacity(128),
+            pool,
         })
 
     }
 
+    /// Use the given packet pool for received packets instead of a new one, this
+    /// is useful to share a pool with the listeners' bundle assemblers, which are
+    /// created before the proxy.
+    pub fn with_pool(mut self, pool: PacketPool) -> Self {
+        self.client.pool = pool.clone();
+        self.server.pool = pool.clone();
+        self.pool = pool;
+        self
+    }
+
+    /// The packet pool used for received packets, listeners can give it to
+    /// their bundle assemblers so that packets are reused once processed.
+    pub fn pool(&self) -> &PacketPool {
+        &self.pool
+    }
+
+    /// Set the policy consulted for each datagram received from the client side,
+    /// datagrams from rejected peers are dropped before reaching the listener.
+    /// The policy must be [`Send`] so that the proxy can be polled from another
+    /// thread.
+    pub fn set_client_policy<P: PeerPolicy + Send + 'static>(&mut self, policy: P) {
+        self.client.handler.policy = Some(Box::new(policy));
+    }
+
+    /// Set the handler called for each datagram rejected by the client policy,
+    /// rejections are not buffered so they are lost if no handler is set.
+    pub fn set_rejection_handler<F: FnMut(PeerRejection) + Send + 'static>(&mut self, handler: F) {
+        self.client.handler.rejection_handler = Some(Box::new(handler));
+    }
+
     pub fn poll(&mut self) -> io::Result<()> {
 
         self.poll.poll(&mut self.events, None)?;
@@ -93,7 +128,8 @@ where
 struct ProxySide<H, L> {
     sock: UdpSocket,
     handler: H,
-    listener: L
+    listener: L,
+    pool: PacketPool,
 }
 
 impl<H, L> ProxySide<H, L>
@@ -102,13 +138,14 @@ where
     L: ProxyListener
 {
     
-    fn new(bind_addr: SocketAddr, mut handler: H, listener: L) -> io::Result<Self> {
+    fn new(bind_addr: SocketAddr, mut handler: H, listener: L, pool: PacketPool) -> io::Result<Self> {
         let mut sock = UdpSocket::bind(bind_addr)?;
         handler.setup(&mut sock)?;
         Ok(Self {
             sock,
             handler,
-            listener
+            listener,
+            pool,
         })
     }
     
@@ -119,13 +156,19 @@ where
         TL: ProxyListener
     {
         loop {
-            let mut packet = Packet::new_boxed(true);
+            let mut packet = self.pool.acquire(true);
             match self.handler.recv(&self.sock, packet.get_raw_data_mut()) {
                 Ok(len) => {
                     self.listener.received(packet, len, to)?;
                 },
-                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
-                Err(e) => return Err(e)
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.pool.release(packet);
+                    break
+                }
+                Err(e) => {
+                    self.pool.release(packet);
+                    return Err(e)
+                }
             }
         }
         Ok(())
@@ -203,12 +246,14 @@ trait ProxySideConnector {
 /// when sending datagrams. *This means that no datagram can be sent if no
 /// one was received before, because we can't know the client peer address.*
 struct ProxyClientHandler {
-    addr: Option<SocketAddr>
+    addr: Option<SocketAddr>,
+    policy: Option<Box<dyn PeerPolicy + Send>>,
+    rejection_handler: Option<Box<dyn FnMut(PeerRejection) + Send>>,
 }
 
 impl ProxyClientHandler {
     fn new() -> Self {
-        Self { addr: None }
+        Self { addr: None, policy: None, rejection_handler: None }
     }
 }
 
@@ -219,9 +264,19 @@ impl ProxySideConnector for ProxyClientHandler {
     }
 
     fn recv(&mut self, from: &UdpSocket, buf: &mut [u8]) -> io::Result<usize> {
-        let (len, orig) = from.recv_from(buf)?;
-        self.addr = Some(orig);
-        Ok(len)
+        loop {
+            let (len, orig) = from.recv_from(buf)?;
+            if let Some(policy) = &mut self.policy {
+                if let PeerDecision::Reject(reason) = policy.check(orig) {
+                    if let Some(handler) = &mut self.rejection_handler {
+                        handler(PeerRejection { addr: orig, reason });
+                    }
+                    continue;
+                }
+            }
+            self.addr = Some(orig);
+            return Ok(len);
+        }
     }
 
     fn send(&mut self, to: &UdpSocket, buf: &[u8]) -> io::Result<usize> {
@@ -263,3 +318,31 @@ impl ProxySideConnector for ProxyServerHandler {
     }
 
 }
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn proxy_send() {
+
+        let mut proxy = Proxy::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:9".parse().unwrap(),
+            ProxyDirectTransfer,
+            ProxyDirectTransfer,
+        ).unwrap();
+
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        proxy.set_client_policy(|_: SocketAddr| PeerDecision::Allow);
+        proxy.set_rejection_handler(move |rejection| sender.send(rejection).unwrap());
+
+        // The proxy can be polled from another thread.
+        std::thread::spawn(move || drop(proxy)).join().unwrap();
+
+    }
+
+}
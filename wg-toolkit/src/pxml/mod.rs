@@ -20,7 +20,8 @@ pub const MAGIC: &[u8; 4] = b"\x45\x4E\xA1\x62";
 
 
 /// A packed XML untyped value.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Element(Box<Element>),
     String(String),
@@ -32,7 +33,8 @@ pub enum Value {
 }
 
 /// A packed element.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Element {
     /// Proper value of a element.
     pub value: Value,
@@ -120,6 +122,87 @@ impl Value {
         if let Self::Affine3(n) = *self { Some(n) } else { None }
     }
 
+    /// Parse a value from its textual XML representation, using the same
+    /// type selection as the game's tools: booleans, integers, floats,
+    /// vectors of 3 floats and matrices of 12 floats, strings otherwise.
+    /// 
+    /// Integers are only selected if their text is canonical, so that the
+    /// text representation of a string such as "007" is kept.
+    pub fn from_text(text: &str) -> Self {
+
+        let trimmed = text.trim();
+
+        match trimmed {
+            "true" => return Self::Boolean(true),
+            "false" => return Self::Boolean(false),
+            _ => {}
+        }
+
+        if let Ok(n) = trimmed.parse::<i64>() {
+            if n.to_string() == trimmed {
+                return Self::Integer(n);
+            }
+        }
+
+        let mut floats = SmallVec::<[f32; 12]>::new();
+        for part in trimmed.split_ascii_whitespace() {
+            match parse_float(part) {
+                Some(n) if floats.len() < 12 => floats.push(n),
+                _ => return Self::String(text.to_string()),
+            }
+        }
+
+        match floats.len() {
+            1 => Self::Float(floats[0]),
+            3 => Self::Vec3(Vec3A::from_slice(&floats)),
+            12 => Self::Affine3(Affine3A::from_cols_slice(&floats)),
+            _ => Self::String(text.to_string()),
+        }
+
+    }
+
+    /// Get the textual XML representation of this value, none is returned
+    /// for elements. Floats are always written with a decimal point in
+    /// order to be parsed back as floats by [`Self::from_text`].
+    pub fn to_text(&self) -> Option<String> {
+        Some(match self {
+            Self::Element(_) => return None,
+            Self::String(s) => s.clone(),
+            Self::Integer(n) => n.to_string(),
+            Self::Boolean(b) => b.to_string(),
+            &Self::Float(n) => format_float(n),
+            Self::Vec3(v) => format_floats(&v.to_array()),
+            Self::Affine3(a) => format_floats(&a.to_cols_array()),
+        })
+    }
+
+}
+
+
+/// Internal function to parse a float from its text, only decimal notation
+/// is accepted, with a mandatory decimal point.
+fn parse_float(text: &str) -> Option<f32> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (int_part, frac_part) = digits.split_once('.')?;
+    let valid = !int_part.is_empty() 
+        && int_part.bytes().all(|c| c.is_ascii_digit()) 
+        && frac_part.bytes().all(|c| c.is_ascii_digit());
+    valid.then(|| text.parse().ok()).flatten()
+}
+
+/// Internal function to format a float, the shortest representation that
+/// is parsed back to the same float is used.
+fn format_float(n: f32) -> String {
+    let mut s = n.to_string();
+    if n.is_finite() && !s.contains('.') {
+        s.push_str(".0");
+    }
+    s
+}
+
+/// Internal function to format many floats separated by spaces.
+fn format_floats(floats: &[f32]) -> String {
+    floats.iter().map(|&n| format_float(n)).collect::<Vec<_>>().join(" ")
 }
 
 
@@ -166,4 +249,69 @@ impl DataType {
         }
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn value_text_heuristics() {
+
+        assert_eq!(Value::from_text("true"), Value::Boolean(true));
+        assert_eq!(Value::from_text(" 42 "), Value::Integer(42));
+        assert_eq!(Value::from_text("-7"), Value::Integer(-7));
+        assert_eq!(Value::from_text("007"), Value::String("007".to_string()));
+        assert_eq!(Value::from_text("1.5"), Value::Float(1.5));
+        assert_eq!(Value::from_text("1.0 -2.5 3.0"), Value::Vec3(Vec3A::new(1.0, -2.5, 3.0)));
+        assert_eq!(Value::from_text("1.0 2.0"), Value::String("1.0 2.0".to_string()));
+        assert_eq!(Value::from_text("nan"), Value::String("nan".to_string()));
+        assert_eq!(Value::from_text("1e5"), Value::String("1e5".to_string()));
+
+        for value in [
+            Value::Float(1.0),
+            Value::Float(-0.1),
+            Value::Float(1e20),
+            Value::Integer(i64::MIN),
+            Value::Vec3(Vec3A::new(0.3, 100.0, -1.25)),
+            Value::Affine3(Affine3A::from_cols_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.5, 11.0, 0.1])),
+        ] {
+            assert_eq!(Value::from_text(&value.to_text().unwrap()), value);
+        }
+
+    }
+
+    #[test]
+    fn element_round_trip() {
+
+        let mut child = Element::new();
+        child.value = Value::String("child value".to_string());
+        child.add_children("zero", Value::Integer(0));
+        child.add_children("small", Value::Integer(-100));
+        child.add_children("large", Value::Integer(1 << 40));
+        child.add_children("yes", Value::Boolean(true));
+        child.add_children("no", Value::Boolean(false));
+
+        let mut root = Element::new();
+        root.value = Value::String(String::new());
+        root.add_children("child", Value::Element(Box::new(child)));
+        root.add_children("float", Value::Float(0.25));
+        root.add_children("vec", Value::Vec3(Vec3A::new(1.0, 2.0, 3.0)));
+        root.add_children("matrix", Value::Affine3(Affine3A::IDENTITY));
+        // The first string is compressed, the second is not canonical base64.
+        root.add_children("base64", Value::String("AAECAw==".to_string()));
+        root.add_children("base64", Value::String("AAECAx==".to_string()));
+        root.add_children("text", Value::String("hello world".to_string()));
+
+        let mut data = Cursor::new(Vec::new());
+        to_writer(&mut data, &root).unwrap();
+        let decoded = from_bytes(data.into_inner()).unwrap();
+
+        assert_eq!(*decoded, root);
+
+    }
+
+}
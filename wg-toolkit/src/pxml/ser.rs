@@ -126,9 +126,11 @@ fn write_value<W: Write + Seek>(writer: &mut W, value: &Value, dict: &HashMap<&S
             write_element(writer, &*child_element, dict).map(|len| (DataType::Element, len))
         }
         Value::String(s) => {
-            // Here we check if the input can possibly be compressed.
+            // Here we check if the input can possibly be compressed, the 
+            // string must be canonical base64 in order to be decoded back 
+            // to the same string.
             if !s.is_empty() && s.len() % 4 == 0 {
-                if let Ok(compressed) = base64::decode(s.as_bytes()) {
+                if let Some(compressed) = base64::decode(s.as_bytes()).ok().filter(|c| base64::encode(c) == *s) {
                     writer.write_all(&compressed[..])?;
                     return Ok((DataType::CompressedString, compressed.len()))
                 }
@@ -0,0 +1,407 @@
+//! Resource cache indexing all files of the game's packages, the index can
+//! be persisted to avoid reading all packages' central directories again.
+
+use std::io::{self, Read, Write, Seek, SeekFrom, BufReader, BufWriter};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use std::fs::{self, File};
+
+use crate::util::io::{WgReadExt, WgWriteExt};
+
+use super::pkg::PackageMetaReader;
+use super::{ResResult, ResError};
+
+
+/// Name of the directory storing packages in the "res/" directory.
+const PACKAGES_DIR_NAME: &str = "packages";
+
+/// Magic and version of the persisted index file.
+const INDEX_MAGIC: &[u8; 4] = b"WGRC";
+const INDEX_VERSION: u32 = 1;
+
+
+/// A cache of all files stored in the game's packages, mapping each file
+/// path to its location in a package. A file present in many packages is
+/// indexed from the first package in name order.
+///
+/// Files are also indexed by their CRC-32, which can be used to find
+/// identical files stored under different paths.
+pub struct ResourceCache {
+    /// Path the "res/" directory.
+    dir_path: PathBuf,
+    /// All indexed packages, their index is used by entries.
+    packages: Vec<CachePackage>,
+    /// All indexed files.
+    entries: Vec<(String, CacheEntry)>,
+    /// Mapping of file paths to their entry index.
+    entries_rev: HashMap<String, usize>,
+    /// Mapping of CRC-32 to entries indices.
+    entries_crc32: HashMap<u32, Vec<usize>>,
+}
+
+/// An indexed package, its length and modification time are used to know
+/// if a persisted index is outdated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CachePackage {
+    name: String,
+    len: u64,
+    modified: u64,
+}
+
+/// Location of a file in a package.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheEntry {
+    /// Index of the package storing the file.
+    pub package: u32,
+    /// Stored data offset within the package.
+    pub data_offset: u64,
+    /// Stored data size.
+    pub data_size: u32,
+    /// CRC32 of the file's data.
+    pub crc32: u32,
+}
+
+impl ResourceCache {
+
+    /// Build a new cache by reading all packages of the given "res/"
+    /// directory.
+    pub fn build<P: Into<PathBuf>>(dir_path: P) -> ResResult<Self> {
+
+        let dir_path = dir_path.into();
+        let mut cache = Self::with_packages(dir_path.clone(), list_packages(&dir_path)?);
+
+        for package_index in 0..cache.packages.len() {
+
+            let package_path = cache.package_path(package_index as u32);
+            let mut reader = PackageMetaReader::new(BufReader::new(File::open(package_path)?))?;
+
+            while let Some(meta) = reader.read_file_meta()? {
+                // Directories are not indexed.
+                if !meta.file_name.ends_with('/') {
+                    cache.insert(meta.file_name, CacheEntry {
+                        package: package_index as u32,
+                        data_offset: meta.data_offset,
+                        data_size: meta.data_size,
+                        crc32: meta.crc32,
+                    });
+                }
+            }
+
+        }
+
+        Ok(cache)
+
+    }
+
+    /// Load the cache from the given index file if it's up to date with
+    /// the packages, if not the cache is rebuilt and the index file is
+    /// overwritten.
+    pub fn load_or_build<P, I>(dir_path: P, index_path: I) -> ResResult<Self>
+    where
+        P: Into<PathBuf>,
+        I: AsRef<Path>,
+    {
+
+        let dir_path = dir_path.into();
+        let index_path = index_path.as_ref();
+
+        if let Ok(file) = File::open(index_path) {
+            if let Ok(cache) = Self::load(dir_path.clone(), BufReader::new(file)) {
+                if !cache.is_outdated()? {
+                    return Ok(cache);
+                }
+            }
+        }
+
+        let cache = Self::build(dir_path)?;
+        let mut writer = BufWriter::new(File::create(index_path)?);
+        cache.save(&mut writer)?;
+        writer.flush()?;
+        Ok(cache)
+
+    }
+
+    /// Load a cache from a persisted index, the index is not checked
+    /// against the packages, see [`Self::is_outdated`].
+    pub fn load<P: Into<PathBuf>, R: Read>(dir_path: P, mut reader: R) -> io::Result<Self> {
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC || reader.read_u32()? != INDEX_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid resource index"));
+        }
+
+        // Counts are not trusted for preallocation, because a corrupted index
+        // should only fail when reading records.
+        let packages_count = reader.read_u32()?;
+        let mut packages = Vec::new();
+        for _ in 0..packages_count {
+            let name_len = reader.read_u16()?;
+            packages.push(CachePackage {
+                name: reader.read_string(name_len as usize)?,
+                len: reader.read_u64()?,
+                modified: reader.read_u64()?,
+            });
+        }
+
+        let mut cache = Self::with_packages(dir_path.into(), packages);
+
+        let entries_count = reader.read_u32()?;
+        for _ in 0..entries_count {
+            let path_len = reader.read_u16()?;
+            let path = reader.read_string(path_len as usize)?;
+            let entry = CacheEntry {
+                package: reader.read_u32()?,
+                data_offset: reader.read_u64()?,
+                data_size: reader.read_u32()?,
+                crc32: reader.read_u32()?,
+            };
+            if entry.package as usize >= cache.packages.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid resource index package"));
+            }
+            cache.insert(path, entry);
+        }
+
+        Ok(cache)
+
+    }
+
+    /// Save the index of this cache, it can be loaded later with
+    /// [`Self::load`].
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_u32(INDEX_VERSION)?;
+
+        writer.write_u32(index_len(self.packages.len())?)?;
+        for package in &self.packages {
+            writer.write_u16(index_len(package.name.len())?)?;
+            writer.write_string(&package.name)?;
+            writer.write_u64(package.len)?;
+            writer.write_u64(package.modified)?;
+        }
+
+        writer.write_u32(index_len(self.entries.len())?)?;
+        for (path, entry) in &self.entries {
+            writer.write_u16(index_len(path.len())?)?;
+            writer.write_string(path)?;
+            writer.write_u32(entry.package)?;
+            writer.write_u64(entry.data_offset)?;
+            writer.write_u32(entry.data_size)?;
+            writer.write_u32(entry.crc32)?;
+        }
+
+        Ok(())
+
+    }
+
+    /// Return true if packages have been added, removed or modified since
+    /// this cache has been built.
+    pub fn is_outdated(&self) -> io::Result<bool> {
+        Ok(list_packages(&self.dir_path)? != self.packages)
+    }
+
+    /// Returns the number of indexed files.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the location of a file from its path.
+    pub fn get(&self, path: &str) -> Option<&CacheEntry> {
+        let path = path.trim_start_matches('/');
+        self.entries_rev.get(path).map(|&idx| &self.entries[idx].1)
+    }
+
+    /// Iterate over the paths of all files with the given CRC-32.
+    pub fn find_by_crc32(&self, crc32: u32) -> impl Iterator<Item = &'_ str> + '_ {
+        self.entries_crc32.get(&crc32)
+            .into_iter()
+            .flatten()
+            .map(|&idx| self.entries[idx].0.as_str())
+    }
+
+    /// Iterate over all indexed files and their location.
+    pub fn iter(&self) -> impl Iterator<Item = (&'_ str, &'_ CacheEntry)> + '_ {
+        self.entries.iter().map(|(path, entry)| (path.as_str(), entry))
+    }
+
+    /// Get the name of a package from its index.
+    #[inline]
+    pub fn package_name(&self, package: u32) -> &str {
+        &self.packages[package as usize].name
+    }
+
+    /// Get the full path of a package from its index.
+    pub fn package_path(&self, package: u32) -> PathBuf {
+        let mut path = self.dir_path.join(PACKAGES_DIR_NAME);
+        path.push(self.package_name(package));
+        path
+    }
+
+    /// Read the whole data of a file, its CRC-32 is checked.
+    pub fn read(&self, path: &str) -> ResResult<Vec<u8>> {
+
+        let entry = self.get(path).ok_or(ResError::FileNotFound)?;
+
+        let mut file = File::open(self.package_path(entry.package))?;
+        file.seek(SeekFrom::Start(entry.data_offset))?;
+
+        let mut data = vec![0; entry.data_size as usize];
+        file.read_exact(&mut data)?;
+
+        if crc32fast::hash(&data) != entry.crc32 {
+            return Err(ResError::Io(io::Error::new(io::ErrorKind::InvalidData, "invalid file crc32")));
+        }
+
+        Ok(data)
+
+    }
+
+    /// Internal function to create an empty cache with the given packages.
+    fn with_packages(dir_path: PathBuf, packages: Vec<CachePackage>) -> Self {
+        Self {
+            dir_path,
+            packages,
+            entries: Vec::new(),
+            entries_rev: HashMap::new(),
+            entries_crc32: HashMap::new(),
+        }
+    }
+
+    /// Internal function to insert an entry, ignored if the path is already
+    /// indexed.
+    fn insert(&mut self, path: String, entry: CacheEntry) {
+        if !self.entries_rev.contains_key(&path) {
+            let idx = self.entries.len();
+            self.entries_rev.insert(path.clone(), idx);
+            self.entries_crc32.entry(entry.crc32).or_default().push(idx);
+            self.entries.push((path, entry));
+        }
+    }
+
+}
+
+
+/// Internal function to convert a length or count to be saved in the index,
+/// returning an invalid input error if it doesn't fit.
+fn index_len<T: TryFrom<usize>>(len: usize) -> io::Result<T> {
+    T::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "resource index length overflow"))
+}
+
+/// Internal function to list all packages of a "res/" directory, sorted
+/// by name.
+fn list_packages(dir_path: &Path) -> io::Result<Vec<CachePackage>> {
+
+    let mut packages = Vec::new();
+
+    for entry in fs::read_dir(dir_path.join(PACKAGES_DIR_NAME))? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            if let Some(name) = entry.file_name().to_str() {
+                if name.ends_with(".pkg") {
+                    packages.push(CachePackage {
+                        name: name.to_string(),
+                        len: metadata.len(),
+                        modified: metadata.modified()
+                            .ok()
+                            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                            .map(|duration| duration.as_secs())
+                            .unwrap_or(0),
+                    });
+                }
+            }
+        }
+    }
+
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn test_cache() -> ResourceCache {
+        let mut cache = ResourceCache::with_packages(PathBuf::from("res"), vec![
+            CachePackage { name: "gui.pkg".to_string(), len: 1024, modified: 1600000000 },
+            CachePackage { name: "shared_content.pkg".to_string(), len: 4096, modified: 1600000001 },
+        ]);
+        cache.insert("gui/maps/icon.dds".to_string(), CacheEntry { package: 0, data_offset: 30, data_size: 100, crc32: 0xDEADBEEF });
+        cache.insert("content/model.model".to_string(), CacheEntry { package: 1, data_offset: 60, data_size: 200, crc32: 0x12345678 });
+        cache.insert("content/copy.model".to_string(), CacheEntry { package: 1, data_offset: 300, data_size: 200, crc32: 0x12345678 });
+        cache
+    }
+
+    #[test]
+    fn index_round_trip() {
+
+        let cache = test_cache();
+        let mut data = Vec::new();
+        cache.save(&mut data).unwrap();
+
+        let loaded = ResourceCache::load("res", &data[..]).unwrap();
+        assert_eq!(loaded.packages, cache.packages);
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.package_name(1), "shared_content.pkg");
+
+        let entry = loaded.get("/content/model.model").unwrap();
+        assert_eq!((entry.package, entry.data_offset, entry.data_size, entry.crc32), (1, 60, 200, 0x12345678));
+
+        let mut same_crc32 = loaded.find_by_crc32(0x12345678).collect::<Vec<_>>();
+        same_crc32.sort();
+        assert_eq!(same_crc32, ["content/copy.model", "content/model.model"]);
+
+    }
+
+    #[test]
+    fn index_truncated() {
+
+        let mut data = Vec::new();
+        test_cache().save(&mut data).unwrap();
+
+        for len in [0, 4, 8, 20, data.len() - 1] {
+            assert!(ResourceCache::load("res", &data[..len]).is_err(), "truncated at {len}");
+        }
+
+    }
+
+    #[test]
+    fn index_huge_counts() {
+
+        // Huge counts without the matching records must fail, not abort.
+        let mut data = Vec::new();
+        data.extend_from_slice(INDEX_MAGIC);
+        data.write_u32(INDEX_VERSION).unwrap();
+        data.write_u32(u32::MAX).unwrap();
+        assert!(ResourceCache::load("res", &data[..]).is_err());
+
+        let mut data = Vec::new();
+        data.extend_from_slice(INDEX_MAGIC);
+        data.write_u32(INDEX_VERSION).unwrap();
+        data.write_u32(0).unwrap();
+        data.write_u32(u32::MAX).unwrap();
+        assert!(ResourceCache::load("res", &data[..]).is_err());
+
+    }
+
+    #[test]
+    fn index_save_overflow() {
+        let mut cache = test_cache();
+        cache.insert("a".repeat(u16::MAX as usize + 1), CacheEntry { package: 0, data_offset: 0, data_size: 0, crc32: 0 });
+        let err = cache.save(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+}
@@ -1,25 +1,28 @@
 //! Game's resources fetching and indexing.
 
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::fs::{File, ReadDir, DirEntry};
-use std::sync::Arc;
-use std::{fs, io};
+use std::io;
 
 pub mod pkg;
-use pkg::{PackageMetaReader, PackageReader, PackageFile};
+mod pattern;
 
-use thiserror::Error;
+#[cfg(feature = "fs")]
+mod fs;
+#[cfg(feature = "fs")]
+pub use fs::{ResFilesystem, ResFile, ResReadDir, ResFind};
+#[cfg(feature = "fs")]
+mod cache;
+#[cfg(feature = "fs")]
+pub use cache::{ResourceCache, CacheEntry};
 
+use thiserror::Error;
 
-/// Name of the directory storing packages in the "res/" directory.
-const PACKAGES_DIR_NAME: &'static str = "packages";
+pub use pattern::ResPattern;
 
 
 /// Options used for opening and indexing the game's resources
 /// filesystem.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResOptions {
     /// Max depth of directories to index.
     /// Default value to 3, optimal because vehicles
@@ -35,395 +38,14 @@ impl Default for ResOptions {
     }
 }
 
-/// A virtual filesystem you can use to walk through the game's
-/// resources. 
-pub struct ResFilesystem {
-    /// Path the "res/" directory.
-    dir_path: PathBuf,
-    /// Cache for opened packages.
-    package_cache: PackageCache,
-    /// Indexing for directory, mapping their path to their
-    /// location, these can be located in the root directory
-    /// and/or many packages. Some directories may not have
-    /// a mapping here, in case of missing directories, just
-    /// refer to one of its parents.
-    /// 
-    /// If some directory is mapped in this index, all its
-    /// parents are also mapped.
-    /// 
-    /// Keys are directory's path without the terminal slash.
-    dir_index: HashMap<String, DirLocations>,
-}
-
-/// Cache for opened packages' archives.
-struct PackageCache {
-    inner: HashMap<String, Arc<PackageReader<File>>>,
-}
-
-/// List of locations for a top level directory in the index.
-#[derive(Default, Debug)]
-struct DirLocations {
-    // The TLD is available in the root "res/" directory.
-    in_root: bool,
-    // Packages where the TLD is available.
-    in_packages: Vec<String>,
-}
-
-impl ResFilesystem {
-
-    pub fn new<P: Into<PathBuf>>(dir_path: P) -> io::Result<Self> {
-        Self::with_options(dir_path, ResOptions::default())
-    }
-
-    pub fn with_options<P: Into<PathBuf>>(dir_path: P, options: ResOptions) -> io::Result<Self> {
-
-        let dir_path = dir_path.into();
-        let mut dir_index: HashMap<String, DirLocations> = HashMap::new();
-
-        // If there are top-level file in root directory.
-        let mut root_tlf = false;
-        for entry in fs::read_dir(&dir_path)? {
-            if let Ok(entry) = entry {
-                let entry_type = entry.file_type()?;
-                if entry_type.is_file() {
-                    // Top-level file.
-                    root_tlf = true;
-                } else if entry_type.is_dir() {
-                    // Top-level directory.
-                    if let Some(dir_name) = entry.file_name().to_str() {
-                        // Packages directory is special and should not be considered as existing.
-                        if dir_name != PACKAGES_DIR_NAME {
-                            dir_index.entry(dir_name.to_string()).or_default().in_root = true;
-                        }
-                    }
-                }
-            }
-        }
-
-        if root_tlf {
-            // If there are files in root directory.
-            dir_index.entry(String::new()).or_default().in_root = true;
-        }
-
-        for entry in fs::read_dir(dir_path.join(PACKAGES_DIR_NAME))? {
-            let entry = entry?;
-            if entry.file_type()?.is_file() {
-                if let Some(package_name) = entry.file_name().to_str() {
-                    if package_name.ends_with(".pkg") {
-                        
-                        println!("========= {package_name} =========");
-
-                        let mut pkg = PackageMetaReader::new(File::open(entry.path())?).unwrap();
-
-                        'files_it: 
-                        while let Some(meta) = pkg.read_file_meta().unwrap() {
-                            
-                            let mut depth = 0;
-                            for ch in meta.file_name.chars().rev() {
-                                if ch == '/' {
-                                    if depth >= options.index_max_depth {
-                                        // Do not index this directory.
-                                        continue 'files_it;
-                                    }
-                                    depth += 1;
-                                } else if depth == 0 {
-                                    // If the first character from the end if not a slash,
-                                    // it's not a directory, so ignore the file.
-                                    continue 'files_it;
-                                }
-                            }
-
-                            // Directory name without terminal slash.
-                            let dir_name = &meta.file_name[..meta.file_name.len() - 1];
-                            if let Some(locs) = dir_index.get_mut(dir_name) {
-                                locs.in_packages.push(package_name.to_string());
-                            } else {
-                                dir_index.insert(dir_name.to_string(), DirLocations { 
-                                    in_root: false, 
-                                    in_packages: vec![package_name.to_string()],
-                                });
-                            }
-
-                        }
-
-                    }
-                }
-            }
-        }
-
-        Ok(Self { 
-            dir_path,
-            package_cache: PackageCache {
-                inner: HashMap::new(),
-            },
-            dir_index,
-        })
-
-    }
-
-    /// Read a directory given a path. This method will success if at least
-    /// one of the packages (or root) actually contains the directory. If
-    /// not, a [`ResError::DirectoryNotFound`].
-    pub fn read_dir(&mut self, path: &str) -> ResResult<ResReadDir> {
-
-        // The canonicalized path needs to end with a slash, this save
-        // some computations and simplify further operations.
-        let mut canon_path = path.trim_start_matches('/').to_string();
-        if !canon_path.ends_with('/') { canon_path.push('/') }
-        // Redefine dir_path as immutable.
-        let canon_path = canon_path;
-
-        // Note that the directory index don't store the last '/'.
-        let mut index_path = &canon_path.as_str()[..canon_path.len() - 1];
-        
-        loop {
-
-            if let Some(locs) = self.dir_index.get(index_path) {
-
-                let mut root_read_dir = None;
-                if locs.in_root {
-                    let file_path = self.dir_path.join(&canon_path);
-                    if file_path.is_dir() {
-                        root_read_dir = Some(fs::read_dir(file_path)?);
-                    }
-                }
-
-                let mut packages = Vec::new();
-                for package in locs.in_packages.iter().rev() {
-                    // Get the opened package and check if it contains the directory.
-                    let pkg = self.package_cache.ensure(package, &self.dir_path)?;
-                    if let Some(dir_index) = pkg.index_from_name(&canon_path) {
-                        // The next file index is directly set to the file following the directory.
-                        packages.push((Arc::clone(&pkg), dir_index + 1));
-                    }
-                }
-
-                if root_read_dir.is_none() && packages.is_empty() {
-                    return Err(ResError::DirectoryNotFound);
-                } else {
-                    return Ok(ResReadDir {
-                        dir_path: canon_path,
-                        root_read_dir,
-                        packages,
-                    })
-                }
-
-            }
-
-            // If we are already on the root directory but it isn't indexed.
-            if index_path.is_empty() {
-                return Err(ResError::DirectoryNotFound);
-            }
-
-            // If the current directory's path is not indexed,
-            // check for its parent. Once we reached 
-            if let Some(pos) = index_path.rfind('/') {
-                index_path = &index_path[..pos];
-            } else {
-                index_path = "";
-            }
-
-        }
-
-    }
-
-    pub fn open(&mut self, path: &str) -> ResResult<ResFile> {
-
-        let full_path = path.trim_matches('/');
-
-        let mut path = full_path;
-        loop {
-
-            let slash_index = path.rfind("/").unwrap_or(0);
-            let parent_path = &path[..slash_index];
-
-            if let Some(locs) = self.dir_index.get(parent_path) {
-                
-                if locs.in_root {
-                    let file_path = self.dir_path.join(full_path);
-                    if file_path.is_file() {
-                        let file = File::open(file_path)?;
-                        return Ok(ResFile(ResFileKind::System(file)));
-                    }
-                }
-
-                for package in &locs.in_packages {
-                    let pkg = self.package_cache.ensure(package, &self.dir_path)?;
-                    if let Some(pkg_file) = pkg.open_by_name(full_path)? {
-                        return Ok(ResFile(ResFileKind::Package(pkg_file)));
-                    }
-                }
-
-                break;
-
-            } else {
-                // If the parent directory can't be found, check its parent.
-                path = parent_path;
-                if path.is_empty() {
-                    break;
-                }
-            }
-
-        }
-
-        Err(ResError::FileNotFound)
-
-    }
-
-}
-
-
-impl PackageCache {
-
-    /// Internal method to ensure that a zip archive is opened.
-    fn ensure(&mut self, package: &String, dir_path: &PathBuf) -> pkg::ReadResult<&Arc<PackageReader<File>>> {
-        Ok(match self.inner.entry(package.clone()) {
-            Entry::Occupied(o) => o.into_mut(),
-            Entry::Vacant(v) => {
-                let mut package_path = dir_path.join(PACKAGES_DIR_NAME);
-                package_path.push(package);
-                v.insert(Arc::new(PackageReader::new(File::open(package_path)?)?))
-            }
-        })
-    }
-
-}
-
-
-/// A seakable reader for a resource file. 
-pub struct ResFile(ResFileKind);
-
-/// Internal kind of resource file reader.
-enum ResFileKind {
-    /// System file.
-    System(File),
-    /// Package file.
-    Package(PackageFile<File>),
-}
-
-
-/// Iterator for a directory in resources.
-pub struct ResReadDir {
-    /// The full path to search for, in packages. 
-    /// **End with a slash.**
-    dir_path: String,
-    /// When some, this std IO [`ReadDir`] should be consumed
-    /// before fetching packages.
-    root_read_dir: Option<ReadDir>,
-    /// Packages to fetch, in reverse order, the last one is the
-    /// current package being read. Packages are associated to
-    /// the file index currently being read.
-    /// 
-    /// Because packages' files are ordered by directories, if 
-    /// the file index no longer points to a file of the dir,
-    /// this means that we finished reading the dir.
-    packages: Vec<(Arc<PackageReader<File>>, usize)>,
-}
-
 /// A directory entry returned from the [`ResReadDir`] iterator.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResDirEntry {
     path: String,
     dir: bool,
 }
 
-impl Iterator for ResReadDir {
-
-    type Item = ResResult<ResDirEntry>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-
-        // FIXME: Remove duplicates from iteration.
-
-        /// Internal function used to convert an std IO [`DirEntry`] result
-        /// into a [`ResDirEntry`] result used by this iterator.
-        fn convert_entry(entry: io::Result<DirEntry>, full_path: &str) -> ResResult<ResDirEntry> {
-
-            let entry = entry?;
-            let file_type = entry.file_type()?;
-            let file_name_raw = entry.file_name();
-            let file_name = file_name_raw.to_str()
-                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "non-utf8 filename"))?;
-
-            Ok(ResDirEntry { 
-                path: format!("{full_path}{file_name}"),
-                dir: file_type.is_dir()
-            })
-
-        }
-        
-        // The iterator state machine starts with the root read directory,
-        // if present. Once all root entries has been yielded, the 
-        if let Some(read_dir) = &mut self.root_read_dir {
-            if let Some(entry) = read_dir.next() {
-                return Some(convert_entry(entry, &self.dir_path));
-            }
-            // If no entry has been found, set the read dir to None to
-            // prevent further iteration of it. Might also free so internal
-            // buffers.
-            self.root_read_dir = None;
-        }
-
-        // Try to get the next file meta to return.
-        // If no package is found, go to the next available package, if
-        // no more package is available, this is the end of the iterator.
-        loop {
-
-            if let Some((
-                current_package, 
-                file_index
-            )) = self.packages.last_mut() {
-
-                while let Some(meta) = current_package.files().get(*file_index) {
-
-                    *file_index += 1;
-
-                    // We only take the file if it starts with our full path.
-                    if meta.file_name.starts_with(&self.dir_path[..]) {
-
-                        // Get the sub path after the common the directory path.
-                        let sub_path = &meta.file_name[self.dir_path.len()..];
-                        // Test if this is a file directly contained by the directory.
-                        let sub_direct = match sub_path.find('/') {
-                            Some(pos) => pos == sub_path.len() - 1, // Directory
-                            None => true // File
-                        };
-
-                        // Do not include terminal slash in entry's path.
-                        let no_slash_path = meta.file_name.strip_suffix('/');
-
-                        if sub_direct {
-                            return Some(Ok(ResDirEntry { 
-                                dir: no_slash_path.is_some(),
-                                path: no_slash_path.unwrap_or(&meta.file_name[..]).to_string(),
-                            }))
-                        }
-                        
-                    } else {
-                        // If the current file don't start with the directory path,
-                        // we reached the end of the directory.
-                        break;
-                    }
-
-                }
-
-                // If we leave the previous loop without returning, this means that 
-                // the current package is exhausted, so we pop it.
-                if self.packages.pop().is_none() {
-                    return None; // Iterator end!
-                }
-
-            } else {
-                // No package remaining to read.
-                return None;
-            }
-
-        }
-
-    }
-
-}
-
 impl ResDirEntry {
 
     /// Get the full path of the entry.
@@ -472,4 +94,4 @@ pub enum ResError {
     /// IO error.
     #[error("io error: {0}")]
     Io(#[from] io::Error),
-}
+}
\ No newline at end of file
@@ -0,0 +1,376 @@
+//! Glob patterns for searching resources, with gitignore-like syntax.
+
+use std::fmt;
+
+
+/// A glob pattern matching resources' paths, the syntax is similar to
+/// gitignore patterns:
+///
+/// - `*` matches any sequence of characters except a slash;
+/// - `?` matches any character except a slash;
+/// - `[abc]`, `[a-z]` and `[!abc]` match a character class;
+/// - `**` as a whole segment matches any number of directories;
+/// - `\` escapes the following character;
+/// - a pattern without slash, other than a trailing one, matches files at
+///   any depth, just like if it was prefixed by `**/`;
+/// - a leading slash only anchors the pattern to the root;
+/// - a trailing slash only matches directories.
+#[derive(Clone)]
+pub struct ResPattern {
+    /// The original pattern.
+    raw: String,
+    /// Segments of the pattern, between slashes.
+    segments: Vec<Segment>,
+    /// Only match directories.
+    dir_only: bool,
+}
+
+/// A segment of a pattern.
+#[derive(Debug, Clone)]
+enum Segment {
+    /// Matches any number of segments.
+    AnyDepth,
+    /// Matches exactly one segment.
+    Glob(Vec<Token>),
+}
+
+/// A token of a segment glob.
+#[derive(Debug, Clone)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyString,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl ResPattern {
+
+    pub fn new(pattern: &str) -> Self {
+
+        let mut raw = pattern.trim();
+        let dir_only = raw.ends_with('/');
+        raw = raw.trim_end_matches('/');
+
+        let anchored = raw.starts_with('/') || raw.contains('/');
+        let raw_trimmed = raw.trim_start_matches('/');
+
+        let mut segments = Vec::new();
+        if !anchored {
+            segments.push(Segment::AnyDepth);
+        }
+
+        for segment in raw_trimmed.split('/').filter(|s| !s.is_empty()) {
+            if segment == "**" {
+                // Consecutive any depth segments are useless.
+                if !matches!(segments.last(), Some(Segment::AnyDepth)) {
+                    segments.push(Segment::AnyDepth);
+                }
+            } else {
+                segments.push(Segment::Glob(parse_glob(segment)));
+            }
+        }
+
+        Self {
+            raw: pattern.to_string(),
+            segments,
+            dir_only,
+        }
+
+    }
+
+    /// Get the original pattern.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// Return the longest directory path that contains all matching paths,
+    /// without trailing slash. This is where searches should start.
+    pub fn base_dir(&self) -> String {
+        let mut base_dir = String::new();
+        // The last segment is the file name, it's never part of the base.
+        let dir_segments = &self.segments[..self.segments.len().saturating_sub(1)];
+        for segment in dir_segments {
+            let Segment::Glob(tokens) = segment else { break };
+            let mut literal = String::new();
+            for token in tokens {
+                match *token {
+                    Token::Char(c) => literal.push(c),
+                    _ => return base_dir,
+                }
+            }
+            if !base_dir.is_empty() {
+                base_dir.push('/');
+            }
+            base_dir.push_str(&literal);
+        }
+        base_dir
+    }
+
+    /// Return true if the given path matches the pattern, the path must be
+    /// relative to the resources' root, leading and trailing slashes are
+    /// ignored.
+    pub fn matches(&self, path: &str, dir: bool) -> bool {
+        if self.dir_only && !dir {
+            return false;
+        }
+        let path = split_path(path);
+        match_segments(&self.segments, &path, false)
+    }
+
+    /// Return true if some paths in the given directory may match the
+    /// pattern, this is used to avoid walking useless directories.
+    pub fn may_match_in(&self, dir_path: &str) -> bool {
+        let path = split_path(dir_path);
+        match_segments(&self.segments, &path, true)
+    }
+
+}
+
+impl fmt::Debug for ResPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResPattern").field(&self.raw).finish()
+    }
+}
+
+
+/// Internal function to split a path in its non-empty segments.
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// Internal function to parse a single segment's glob.
+fn parse_glob(segment: &str) -> Vec<Token> {
+
+    let mut tokens = Vec::new();
+    let mut chars = segment.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' => {
+                // Consecutive stars in a segment are the same as one.
+                while chars.peek() == Some(&'*') {
+                    chars.next();
+                }
+                Token::AnyString
+            }
+            '?' => Token::AnyChar,
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            '[' => {
+                // Parse the class on a clone, if it's not closed the
+                // bracket is taken literally.
+                let mut class_chars = chars.clone();
+                let negated = matches!(class_chars.peek(), Some('!' | '^'));
+                if negated {
+                    class_chars.next();
+                }
+                let mut ranges = Vec::new();
+                let mut closed = false;
+                while let Some(start) = class_chars.next() {
+                    if start == ']' && !ranges.is_empty() {
+                        closed = true;
+                        break;
+                    }
+                    let mut end = start;
+                    if class_chars.peek() == Some(&'-') {
+                        let mut range_chars = class_chars.clone();
+                        range_chars.next();
+                        if let Some(&range_end) = range_chars.peek() {
+                            if range_end != ']' {
+                                end = range_end;
+                                range_chars.next();
+                                class_chars = range_chars;
+                            }
+                        }
+                    }
+                    ranges.push((start, end));
+                }
+                if closed {
+                    chars = class_chars;
+                    Token::Class { negated, ranges }
+                } else {
+                    Token::Char('[')
+                }
+            }
+            c => Token::Char(c),
+        });
+    }
+
+    tokens
+
+}
+
+/// Internal function to match pattern segments against path segments, if
+/// prefix is true, the path is allowed to end before the pattern.
+fn match_segments(segments: &[Segment], path: &[&str], prefix: bool) -> bool {
+    let names = path.iter().map(|name| name.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+    // Results are memoized for each position in segments and path, so any
+    // depth segments don't make the matching exponential.
+    let mut memo = vec![None; (segments.len() + 1) * (names.len() + 1)];
+    match_segments_from(segments, &names, prefix, 0, 0, &mut memo)
+}
+
+/// Internal function to match pattern segments from the given index against
+/// path segments from the given index, see [`match_segments`].
+fn match_segments_from(
+    segments: &[Segment],
+    names: &[Vec<char>],
+    prefix: bool,
+    segment_index: usize,
+    name_index: usize,
+    memo: &mut [Option<bool>],
+) -> bool {
+
+    let memo_index = segment_index * (names.len() + 1) + name_index;
+    if let Some(matched) = memo[memo_index] {
+        return matched;
+    }
+
+    let path_empty = name_index == names.len();
+    let matched = match segments.get(segment_index) {
+        None => path_empty,
+        Some(_) if prefix && path_empty => true,
+        Some(Segment::AnyDepth) => {
+            (name_index..=names.len()).any(|next_name_index| {
+                match_segments_from(segments, names, prefix, segment_index + 1, next_name_index, memo)
+            })
+        }
+        Some(Segment::Glob(tokens)) => {
+            !path_empty
+                && match_glob(tokens, &names[name_index])
+                && match_segments_from(segments, names, prefix, segment_index + 1, name_index + 1, memo)
+        }
+    };
+
+    memo[memo_index] = Some(matched);
+    matched
+
+}
+
+/// Internal function to match a segment's glob against a path segment. On
+/// mismatch, only the last `*` is extended by one character and the matching
+/// restarts after it, which is enough because any earlier `*` would only
+/// consume characters that the last one can consume.
+fn match_glob(tokens: &[Token], name: &[char]) -> bool {
+
+    let mut token_index = 0;
+    let mut name_index = 0;
+    // Token index after the last star, and name index where it stops.
+    let mut last_star = None;
+
+    while name_index < name.len() {
+        match tokens.get(token_index) {
+            Some(Token::AnyString) => {
+                last_star = Some((token_index + 1, name_index));
+                token_index += 1;
+                continue;
+            }
+            Some(token) if match_token(token, name[name_index]) => {
+                token_index += 1;
+                name_index += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match last_star {
+            Some((star_token_index, star_name_index)) => {
+                last_star = Some((star_token_index, star_name_index + 1));
+                token_index = star_token_index;
+                name_index = star_name_index + 1;
+            }
+            None => return false,
+        }
+    }
+
+    tokens[token_index..].iter().all(|token| matches!(token, Token::AnyString))
+
+}
+
+/// Internal function to match a single character token.
+fn match_token(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => c == *expected,
+        Token::AnyChar => true,
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|&(start, end)| c >= start && c <= end) != *negated
+        }
+        Token::AnyString => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn pattern_matches() {
+
+        let pattern = ResPattern::new("**/*.def");
+        assert_eq!(pattern.base_dir(), "");
+        assert!(pattern.matches("scripts/entity_defs/Avatar.def", false));
+        assert!(pattern.matches("Avatar.def", false));
+        assert!(!pattern.matches("scripts/Avatar.def.bak", false));
+
+        let pattern = ResPattern::new("*.xml");
+        assert!(pattern.matches("gui/maps/list.xml", false));
+
+        let pattern = ResPattern::new("/scripts/entity_defs/[A-Z]?*.def");
+        assert_eq!(pattern.base_dir(), "scripts/entity_defs");
+        assert!(pattern.matches("scripts/entity_defs/Avatar.def", false));
+        assert!(!pattern.matches("scripts/entity_defs/avatar.def", false));
+        assert!(!pattern.matches("scripts/entity_defs/interfaces/Wheels.def", false));
+        assert!(pattern.may_match_in("scripts"));
+        assert!(!pattern.may_match_in("gui"));
+
+        let pattern = ResPattern::new("spaces/*/");
+        assert!(pattern.matches("spaces/01_karelia", true));
+        assert!(!pattern.matches("spaces/01_karelia", false));
+        assert!(!pattern.matches("spaces/01_karelia/space.bin", false));
+
+    }
+
+    #[test]
+    fn pattern_globs() {
+
+        let pattern = ResPattern::new("/a*b?c*");
+        assert!(pattern.matches("abxc", false));
+        assert!(pattern.matches("axxbbxcxx", false));
+        assert!(!pattern.matches("abc", false));
+
+        let pattern = ResPattern::new("/*[!0-9]\\*");
+        assert!(pattern.matches("12a*", false));
+        assert!(!pattern.matches("12a", false));
+        assert!(!pattern.matches("123*", false));
+
+        let pattern = ResPattern::new("/a/**/b/**/c");
+        assert!(pattern.matches("a/b/c", false));
+        assert!(pattern.matches("a/x/b/y/b/c", false));
+        assert!(!pattern.matches("a/c/b", false));
+        assert!(pattern.may_match_in("a/x/b"));
+        assert!(!pattern.may_match_in("x"));
+
+    }
+
+    #[test]
+    fn pattern_pathological() {
+
+        // These patterns take exponential time with a naive backtracking.
+        let name = "a".repeat(100);
+        let pattern = ResPattern::new(&format!("/{}b", "*a".repeat(20)));
+        assert!(!pattern.matches(&name, false));
+        assert!(pattern.matches(&format!("{name}b"), false));
+
+        let path = vec!["a"; 100].join("/");
+        let pattern = ResPattern::new(&format!("/{}b", "**/a/".repeat(20)));
+        assert!(!pattern.matches(&path, false));
+        assert!(pattern.matches(&format!("{path}/b"), false));
+        assert!(pattern.may_match_in(&path));
+
+    }
+
+}
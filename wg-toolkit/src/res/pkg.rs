@@ -6,13 +6,13 @@
 //! Following official specification: 
 //! https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
 
-use std::io::{self, Seek, Read, SeekFrom, BufReader, BufRead};
+use std::io::{self, Seek, Read, Write, SeekFrom, BufReader, BufRead};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use thiserror::Error;
 
-use crate::util::io::WgReadExt;
+use crate::util::io::{WgReadExt, WgWriteExt};
 
 
 /// Signature for the Local File Header structure.
@@ -24,6 +24,15 @@ const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
 /// Signature for the end of central directory.
 const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
 
+/// Minimum size of the Local File Header, without file name and extra field.
+const LOCAL_FILE_HEADER_SIZE: u64 = 30;
+
+/// Version needed to extract stored files, also used as version made by.
+const VERSION: u16 = 10;
+
+/// Last modification date of all written files, 1980-01-01 in DOS format.
+const DOS_DATE: u16 = 0x0021;
+
 
 /// A package-specialized ZIP header reader. This reader can be used for a
 /// file-by-file Local File Header reading.
@@ -38,6 +47,7 @@ pub struct PackageMetaReader<R> {
 /// 
 /// This structure is also internally used by the [`PackageReader`] structure.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PackageFileMeta {
     /// Name of the package's file.
     pub file_name: String,
@@ -459,6 +469,280 @@ impl<R: Read + Seek> Seek for PackageFile<R> {
 }
 
 
+/// A package-specialized ZIP writer, files are always stored without
+/// compression and without any flag, as expected by the game.
+/// 
+/// Files' data is written as soon as they are added, the central directory
+/// is only written when finishing the package, see [`Self::finish`].
+pub struct PackageWriter<W> {
+    inner: W,
+    files: Vec<PackageFileMeta>,
+    files_rev: HashMap<String, usize>,
+    /// Offset where the next Local File Header will be written.
+    offset: u64,
+    /// Length of the previous package when patching one, the end of the
+    /// new package is never placed before it, so that no byte of the
+    /// previous central directory is left after the new one.
+    min_len: u64,
+}
+
+impl<W> PackageWriter<W>
+where
+    W: Write + Seek
+{
+
+    /// Create a writer for a new package, written from the start of the 
+    /// given writer.
+    pub fn new(mut writer: W) -> WriteResult<Self> {
+        writer.seek(SeekFrom::Start(0))?;
+        Ok(Self {
+            inner: writer,
+            files: Vec::new(),
+            files_rev: HashMap::new(),
+            offset: 0,
+            min_len: 0,
+        })
+    }
+
+    /// Returns the number of files that will be stored in the package.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if no file will be stored in the package.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    #[inline]
+    pub fn files(&self) -> &[PackageFileMeta] {
+        &self.files[..]
+    }
+
+    /// Add a file to the package with all the data of the given reader. If
+    /// a file with the same name already exists, it is replaced in the 
+    /// central directory but its data is left as dead space in the package.
+    pub fn add_file<R: Read>(&mut self, file_name: &str, mut reader: R) -> WriteResult<()> {
+
+        let header_offset = self.offset;
+        self.write_local_header(file_name, 0, 0)?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut data_size = 0u64;
+        let mut buf = [0; 8192];
+
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            self.inner.write_all(&buf[..len])?;
+            hasher.update(&buf[..len]);
+            data_size += len as u64;
+        }
+
+        let data_size = u32::try_from(data_size)
+            .map_err(|_| WriteError::FileTooLarge(file_name.to_string()))?;
+        let crc32 = hasher.finalize();
+
+        // Go back to the header to write the CRC-32 and sizes, the cursor
+        // is restored when writing the next header.
+        const LOCAL_HEADER_CRC32_OFFSET: u64 = 14;
+        self.inner.seek(SeekFrom::Start(header_offset + LOCAL_HEADER_CRC32_OFFSET))?;
+        self.inner.write_u32(crc32)?;
+        self.inner.write_u32(data_size)?;
+        self.inner.write_u32(data_size)?;
+
+        let data_offset = header_offset + LOCAL_FILE_HEADER_SIZE + file_name.len() as u64;
+        self.offset = data_offset + data_size as u64;
+
+        self.push_file(PackageFileMeta {
+            file_name: file_name.to_string(),
+            data_size,
+            data_offset,
+            header_offset,
+            crc32,
+        });
+
+        Ok(())
+
+    }
+
+    /// Add a directory entry to the package, a trailing slash is added to
+    /// the name if not already present.
+    pub fn add_directory(&mut self, dir_name: &str) -> WriteResult<()> {
+
+        let mut file_name = dir_name.to_string();
+        if !file_name.ends_with('/') {
+            file_name.push('/');
+        }
+
+        let header_offset = self.offset;
+        self.write_local_header(&file_name, 0, 0)?;
+
+        let data_offset = header_offset + LOCAL_FILE_HEADER_SIZE + file_name.len() as u64;
+        self.offset = data_offset;
+
+        self.push_file(PackageFileMeta {
+            file_name,
+            data_size: 0,
+            data_offset,
+            header_offset,
+            crc32: 0,
+        });
+
+        Ok(())
+
+    }
+
+    /// Finish the package by writing the central directory, the inner 
+    /// writer is returned and is placed at the end of the package.
+    pub fn finish(mut self) -> WriteResult<W> {
+
+        if self.files.len() > u16::MAX as usize {
+            return Err(WriteError::TooManyFiles);
+        }
+
+        // When patching a package, pad the space before the central directory
+        // so that the end of central directory is still the end of the file.
+        let directory_len = self.files.iter()
+            .map(|meta| 46 + meta.file_name.len() as u64)
+            .sum::<u64>() + 22;
+
+        self.inner.seek(SeekFrom::Start(self.offset))?;
+        let padding = self.min_len.saturating_sub(self.offset + directory_len);
+        io::copy(&mut io::repeat(0).take(padding), &mut self.inner)?;
+
+        let central_directory_offset = u32::try_from(self.offset + padding)
+            .map_err(|_| WriteError::PackageTooLarge)?;
+
+        let mut central_directory_size = 0u32;
+        for meta in &self.files {
+
+            let header_offset = u32::try_from(meta.header_offset)
+                .map_err(|_| WriteError::PackageTooLarge)?;
+
+            self.inner.write_u32(CENTRAL_DIRECTORY_HEADER_SIGNATURE)?;
+            self.inner.write_u16(VERSION)?; // Version made by
+            self.inner.write_u16(VERSION)?; // Version needed
+            self.inner.write_u16(0)?; // Flags
+            self.inner.write_u16(0)?; // Compression method
+            self.inner.write_u16(0)?; // Last mod file time
+            self.inner.write_u16(DOS_DATE)?; // Last mod file date
+            self.inner.write_u32(meta.crc32)?;
+            self.inner.write_u32(meta.data_size)?; // Compressed size
+            self.inner.write_u32(meta.data_size)?; // Uncompressed size
+            self.inner.write_u16(meta.file_name.len() as u16)?;
+            self.inner.write_u16(0)?; // Extra field length
+            self.inner.write_u16(0)?; // File comment length
+            self.inner.write_u16(0)?; // Disk number start
+            self.inner.write_u16(0)?; // Internal file attributes
+            self.inner.write_u32(0)?; // External file attributes
+            self.inner.write_u32(header_offset)?;
+            self.inner.write_string(&meta.file_name)?;
+
+            central_directory_size += 46 + meta.file_name.len() as u32;
+
+        }
+
+        self.inner.write_u32(END_OF_CENTRAL_DIRECTORY_SIGNATURE)?;
+        self.inner.write_u16(0)?; // Disk number
+        self.inner.write_u16(0)?; // Disk with central directory
+        self.inner.write_u16(self.files.len() as u16)?; // Files on this disk
+        self.inner.write_u16(self.files.len() as u16)?; // Files
+        self.inner.write_u32(central_directory_size)?;
+        self.inner.write_u32(central_directory_offset)?;
+        self.inner.write_u16(0)?; // Comment length
+
+        self.inner.flush()?;
+        Ok(self.inner)
+
+    }
+
+    /// Internal function to write a Local File Header at the current write
+    /// offset, the cursor is placed just after the header.
+    fn write_local_header(&mut self, file_name: &str, crc32: u32, data_size: u32) -> WriteResult<()> {
+
+        if file_name.len() > u16::MAX as usize {
+            return Err(WriteError::FileNameTooLong(file_name.to_string()));
+        }
+
+        self.inner.seek(SeekFrom::Start(self.offset))?;
+        self.inner.write_u32(LOCAL_FILE_HEADER_SIGNATURE)?;
+        self.inner.write_u16(VERSION)?; // Version needed
+        self.inner.write_u16(0)?; // Flags
+        self.inner.write_u16(0)?; // Compression method
+        self.inner.write_u16(0)?; // Last mod file time
+        self.inner.write_u16(DOS_DATE)?; // Last mod file date
+        self.inner.write_u32(crc32)?;
+        self.inner.write_u32(data_size)?; // Compressed size
+        self.inner.write_u32(data_size)?; // Uncompressed size
+        self.inner.write_u16(file_name.len() as u16)?;
+        self.inner.write_u16(0)?; // Extra field length
+        self.inner.write_string(file_name)?;
+        Ok(())
+
+    }
+
+    /// Internal function to add or replace a file in the central directory.
+    fn push_file(&mut self, meta: PackageFileMeta) {
+        match self.files_rev.get(&meta.file_name) {
+            Some(&idx) => self.files[idx] = meta,
+            None => {
+                self.files_rev.insert(meta.file_name.clone(), self.files.len());
+                self.files.push(meta);
+            }
+        }
+    }
+
+}
+
+impl<W> PackageWriter<W>
+where
+    W: Read + Write + Seek
+{
+
+    /// Open an existing package in order to add or replace files. New data
+    /// is written over the previous central directory, which is rewritten
+    /// when finishing the package.
+    /// 
+    /// Replaced files' data is not removed, the package must be rebuilt 
+    /// with [`Self::new`] in order to reclaim this space.
+    /// 
+    /// The package is modified in place and is not valid until finished,
+    /// if [`Self::finish`] is not called or fails, the package is left
+    /// corrupted, so a copy should be given if the original must be kept.
+    pub fn open(mut reader: W) -> WriteResult<Self> {
+
+        let min_len = reader.seek(SeekFrom::End(0))?;
+        let mut files = Vec::new();
+        let mut files_rev = HashMap::new();
+        let mut offset = 0;
+
+        let mut meta_reader = PackageMetaReader::new(reader)?;
+        while let Some(meta) = meta_reader.read_file_meta()? {
+            offset = offset.max(meta.data_offset + meta.data_size as u64);
+            files_rev.insert(meta.file_name.clone(), files.len());
+            files.push(meta);
+        }
+
+        Ok(Self {
+            inner: meta_reader.into_inner(),
+            files,
+            files_rev,
+            offset,
+            min_len,
+        })
+
+    }
+
+}
+
+
 /// Result type alias for [`ReadError`] error type.
 pub type ReadResult<T> = Result<T, ReadError>;
 
@@ -495,3 +779,127 @@ pub enum ReadError {
     #[error("io error: {0}")]
     Io(#[from] io::Error),
 }
+
+
+/// Result type alias for [`WriteError`] error type.
+pub type WriteResult<T> = Result<T, WriteError>;
+
+/// Errors that can happen while writing a package.
+#[derive(Debug, Error)]
+pub enum WriteError {
+    /// A file name is too long to be stored in the package.
+    #[error("file name too long: {0}")]
+    FileNameTooLong(String),
+    /// A file's data is too large to be stored in the package.
+    #[error("file too large: {0}")]
+    FileTooLarge(String),
+    /// Too many files to store in the package.
+    #[error("too many files")]
+    TooManyFiles,
+    /// The package exceeds the maximum size of a package.
+    #[error("package too large")]
+    PackageTooLarge,
+    /// Error while reading an existing package to patch.
+    #[error("read error: {0}")]
+    Read(#[from] ReadError),
+    /// IO error while writing.
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Read the whole data of a package's file.
+    fn read_file<R: Read + Seek>(package: &PackageReader<R>, file_name: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        package.open_by_name(file_name).unwrap().unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    /// Create a package with two files and a directory.
+    fn new_package() -> Vec<u8> {
+        let mut writer = PackageWriter::new(Cursor::new(Vec::new())).unwrap();
+        assert!(writer.is_empty());
+        writer.add_file("a.txt", &b"hello"[..]).unwrap();
+        writer.add_directory("dir").unwrap();
+        writer.add_file("dir/b.bin", &(0..10000).map(|i| i as u8).collect::<Vec<u8>>()[..]).unwrap();
+        assert_eq!(writer.len(), 3);
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn write_new() {
+
+        let data = new_package();
+        let b_data = (0..10000).map(|i| i as u8).collect::<Vec<u8>>();
+
+        let mut meta_reader = PackageMetaReader::new(Cursor::new(&data[..])).unwrap();
+        let mut metas = Vec::new();
+        while let Some(meta) = meta_reader.read_file_meta().unwrap() {
+            metas.push(meta);
+        }
+
+        assert_eq!(metas.iter().map(|meta| meta.file_name.as_str()).collect::<Vec<_>>(), ["a.txt", "dir/", "dir/b.bin"]);
+        assert_eq!(metas[0].crc32, crc32fast::hash(b"hello"));
+        assert_eq!(metas[0].data_size, 5);
+        assert_eq!(metas[1].crc32, 0);
+        assert_eq!(metas[1].data_size, 0);
+        assert_eq!(metas[2].crc32, crc32fast::hash(&b_data));
+        assert_eq!(metas[2].data_size, 10000);
+
+        let package = PackageReader::new(Cursor::new(&data[..])).unwrap();
+        assert_eq!(package.len(), 3);
+        assert_eq!(read_file(&package, "a.txt"), b"hello");
+        assert_eq!(read_file(&package, "dir/b.bin"), b_data);
+        assert!(matches!(package.open_by_name("dir/"), Err(ReadError::NoData)));
+
+    }
+
+    #[test]
+    fn write_open_replace() {
+
+        let data = new_package();
+
+        let mut writer = PackageWriter::open(Cursor::new(data)).unwrap();
+        assert_eq!(writer.len(), 3);
+        writer.add_file("a.txt", &b"world!"[..]).unwrap();
+        writer.add_file("c.txt", &b"new"[..]).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let package = PackageReader::new(Cursor::new(&data[..])).unwrap();
+        assert_eq!(package.file_names().collect::<Vec<_>>(), ["a.txt", "dir/", "dir/b.bin", "c.txt"]);
+        assert_eq!(package.files()[0].crc32, crc32fast::hash(b"world!"));
+        assert_eq!(read_file(&package, "a.txt"), b"world!");
+        assert_eq!(read_file(&package, "dir/b.bin"), (0..10000).map(|i| i as u8).collect::<Vec<u8>>());
+        assert_eq!(read_file(&package, "c.txt"), b"new");
+
+    }
+
+    #[test]
+    fn write_open_trailing() {
+
+        // Trailing bytes after the end of central directory must not be
+        // left after the new one.
+        let mut data = new_package();
+        data.extend_from_slice(&[0xAB; 10]);
+        let len = data.len();
+
+        let writer = PackageWriter::open(Cursor::new(data)).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        assert_eq!(data.len(), len);
+        assert_eq!(data[len - 22..len - 18], END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+
+        let package = PackageReader::new(Cursor::new(&data[..])).unwrap();
+        assert_eq!(package.len(), 3);
+        assert_eq!(read_file(&package, "a.txt"), b"hello");
+
+    }
+
+}
@@ -6,6 +6,7 @@ use crate::util::io::WgReadExt;
 
 /// AssetList section, defines a list of assets for this space.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BWAL {
     pub assets: Vec<AssetInfo>
 }
@@ -43,6 +44,7 @@ impl Section for BWAL {
 /// An compiled space asset info.
 /// Decoded by [BWAL] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AssetInfo {
     pub asset_type: AssetType,
     pub string_fnv: u32
@@ -52,6 +54,7 @@ pub struct AssetInfo {
 /// An asset type for an [AssetInfo].
 /// Decoded by [BWAL] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AssetType {
     ParticlesResource,
     WaterReflectionTexture,
@@ -6,6 +6,7 @@ use crate::util::io::WgReadExt;
 
 /// CompiledSpaceSettings section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BWCS {
     pub values: [f32; 6]
 }
@@ -7,6 +7,7 @@ use crate::util::io::WgReadExt;
 
 /// StaticGeometry section, defines models and positions.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BWSG {
     pub strings: HashMap<u32, String>,
     pub models: Vec<ModelInfo>,
@@ -55,6 +56,7 @@ impl Section for BWSG {
 /// A model information with its resources.
 /// Decoded by [BWSG] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModelInfo {
     pub vertices_fnv: u32,
     pub id_from: u32,
@@ -67,6 +69,7 @@ pub struct ModelInfo {
 /// A position information.
 /// Decoded by [BWSG] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PositionInfo {
     pub typ: u64,
     /// Size of vertices block from .primitives
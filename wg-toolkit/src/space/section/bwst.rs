@@ -8,6 +8,7 @@ use crate::util::fnv::fnv1a_64;
 
 /// StringTable section, providing a mapping from strings' FNV hashes to strings.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BWST {
     pub strings: HashMap<u32, String>
 }
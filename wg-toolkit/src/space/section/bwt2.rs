@@ -7,6 +7,7 @@ use crate::util::io::WgReadExt;
 /// Terrain2 section, providing many information about `cdata_processed` files and many
 /// settings for the terrain.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BWT2 {
     pub settings1: TerrainSettings1,
     pub settings2: TerrainSettings2,
@@ -133,6 +134,7 @@ impl Section for BWT2 {
 /// Terrain settings v1.
 /// Decoded by [BWT2] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainSettings1 {
     /// space.settings/chunkSize or 100.0 by default
     pub chunk_size: f32,
@@ -155,6 +157,7 @@ pub struct TerrainSettings1 {
 /// Each chunk has a size defined by [chunk_size](TerrainSettings1.chunk_size).
 /// Decoded by [BWT2] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainChunk {
     /// Resource FNV hash, you can find the path to the `cdata_processed` archive by resolving
     /// this hash in the [BWST](super::BWST) section.
@@ -167,6 +170,7 @@ pub struct TerrainChunk {
 /// Terrain settings v2.
 /// Decoded by [BWT2] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TerrainSettings2 {
     /// space.settings/terrain/version
     pub terrain_version: u32,
@@ -221,6 +225,7 @@ pub struct TerrainSettings2 {
 /// Definition of a cascade in the terrain.
 /// Decoded by [BWT2] section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OutlandCascade {
     pub extent_min: [f32; 3],
     pub extent_max: [f32; 3],
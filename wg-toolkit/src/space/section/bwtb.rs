@@ -8,6 +8,7 @@ use crate::util::io::WgReadExt;
 
 /// Header section, defining all offsets for real sections. This section is a fake section
 /// and doesn't implement the [Section](super::Section) trait.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BWTB {
     pub root: SectionMeta,
     pub sections: Vec<SectionMeta>,
@@ -47,6 +48,7 @@ impl BWTB {
 
 /// Metadata for section, its offset and length. Sections count is an internal value only
 /// used by the fake [BWTB] header section.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SectionMeta {
     pub id: SectionId,
     pub off: usize,
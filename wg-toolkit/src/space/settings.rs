@@ -0,0 +1,253 @@
+//! Utilities to parse the `space.settings` packed XML file of a space.
+
+use std::io::{Seek, Read};
+
+use glam::Vec3;
+use thiserror::Error;
+
+use crate::pxml::{self, Value, Element};
+
+
+/// Default size of a chunk on both axes, used if not given in settings.
+pub const DEFAULT_CHUNK_SIZE: f32 = 100.0;
+
+
+/// Try to read a space settings file from a seekable reader.
+///
+/// *The content will be read starting from the inital position
+/// of the reader.*
+pub fn from_reader<R: Read + Seek>(reader: R) -> Result<SpaceDescriptor, DeError> {
+    let root_elt = pxml::from_reader(reader)?;
+    from_element(&root_elt)
+}
+
+/// Read a space settings from its packed XML root element.
+pub fn from_element(root_elt: &Element) -> Result<SpaceDescriptor, DeError> {
+
+    let bounds_elt = root_elt
+        .get_child("bounds").ok_or(DeError::MissingBounds)?
+        .as_element().ok_or(DeError::MissingBounds)?;
+
+    let read_bound = |key: &str| {
+        let bound = bounds_elt.get_child(key).and_then(value_as_integer).ok_or(DeError::MissingBounds)?;
+        i32::try_from(bound).map_err(|_| DeError::MissingBounds)
+    };
+
+    let min_x = read_bound("minX")?;
+    let max_x = read_bound("maxX")?;
+    let min_y = read_bound("minY")?;
+    let max_y = read_bound("maxY")?;
+
+    if min_x > max_x || min_y > max_y {
+        return Err(DeError::MissingBounds);
+    }
+
+    let chunk_size = match root_elt.get_child("chunkSize") {
+        Some(value) => value_as_float(value).ok_or(DeError::InvalidChunkSize)?,
+        None => DEFAULT_CHUNK_SIZE
+    };
+
+    if chunk_size <= 0.0 {
+        return Err(DeError::InvalidChunkSize);
+    }
+
+    let mut skyboxes = Vec::new();
+    if let Some(skyboxes_elt) = root_elt.get_child("skyBoxes").and_then(Value::as_element) {
+        for (_, value) in skyboxes_elt.iter_children_all() {
+            if let Some(skybox) = value_as_string(value) {
+                skyboxes.push(skybox.clone());
+            }
+        }
+    }
+
+    Ok(SpaceDescriptor {
+        min_x,
+        max_x,
+        min_y,
+        max_y,
+        chunk_size,
+        time_of_day: root_elt.get_child("timeOfDay").and_then(value_as_string).cloned(),
+        sky_gradient_dome: root_elt.get_child("skyGradientDome").and_then(value_as_string).cloned(),
+        skyboxes,
+        terrain_version: root_elt.get_child("terrain")
+            .and_then(Value::as_element)
+            .and_then(|elt| elt.get_child("version"))
+            .and_then(value_as_integer)
+            .map(|version| version as u32),
+    })
+
+}
+
+
+/// Internal function to get an integer from a value, integers are sometimes
+/// stored as strings in settings files.
+fn value_as_integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(n) => Some(*n),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Element(elt) => value_as_integer(&elt.value),
+        _ => None
+    }
+}
+
+/// Internal function to get a float from a value, see [`value_as_integer`].
+fn value_as_float(value: &Value) -> Option<f32> {
+    match value {
+        Value::Float(n) => Some(*n),
+        Value::Integer(n) => Some(*n as f32),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Element(elt) => value_as_float(&elt.value),
+        _ => None
+    }
+}
+
+/// Internal function to get a string from a value.
+fn value_as_string(value: &Value) -> Option<&String> {
+    match value {
+        Value::String(s) => Some(s),
+        Value::Element(elt) => elt.value.as_string(),
+        _ => None
+    }
+}
+
+
+/// Description of a space, read from its settings.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpaceDescriptor {
+    /// Minimum chunk coordinate on the X axis, inclusive.
+    pub min_x: i32,
+    /// Maximum chunk coordinate on the X axis, inclusive.
+    pub max_x: i32,
+    /// Minimum chunk coordinate on the Y axis (world Z axis), inclusive.
+    pub min_y: i32,
+    /// Maximum chunk coordinate on the Y axis (world Z axis), inclusive.
+    pub max_y: i32,
+    /// Size of a chunk on both axes, in world units.
+    pub chunk_size: f32,
+    /// Path to the time of day settings.
+    pub time_of_day: Option<String>,
+    /// Path to the sky gradient dome settings.
+    pub sky_gradient_dome: Option<String>,
+    /// Paths to the skybox models.
+    pub skyboxes: Vec<String>,
+    /// Version of the terrain, if specified.
+    pub terrain_version: Option<u32>,
+}
+
+impl SpaceDescriptor {
+
+    /// Number of chunks on the X and Y axes, computed on 64 bits because the
+    /// bounds may cover the whole 32 bits range.
+    #[inline]
+    pub fn chunk_dimensions(&self) -> (u64, u64) {
+        let width = self.max_x as i64 - self.min_x as i64 + 1;
+        let height = self.max_y as i64 - self.min_y as i64 + 1;
+        (width as u64, height as u64)
+    }
+
+    /// World bounds of the space on the X/Z plane, as minimum and maximum.
+    pub fn world_bounds(&self) -> (Vec3, Vec3) {
+        let max_x = (self.max_x as i64 + 1) as f32 * self.chunk_size;
+        let max_y = (self.max_y as i64 + 1) as f32 * self.chunk_size;
+        (self.chunk_to_world(self.min_x, self.min_y), Vec3::new(max_x, 0.0, max_y))
+    }
+
+    /// Return true if the given chunk coordinates are in the space.
+    #[inline]
+    pub fn contains_chunk(&self, x: i32, y: i32) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+
+    /// World position of the origin (minimum corner) of the given chunk.
+    #[inline]
+    pub fn chunk_to_world(&self, x: i32, y: i32) -> Vec3 {
+        Vec3::new(x as f32 * self.chunk_size, 0.0, y as f32 * self.chunk_size)
+    }
+
+    /// Coordinates of the chunk containing the given world position, the
+    /// chunk may be out of the space.
+    #[inline]
+    pub fn world_to_chunk(&self, pos: Vec3) -> (i32, i32) {
+        ((pos.x / self.chunk_size).floor() as i32, (pos.z / self.chunk_size).floor() as i32)
+    }
+
+}
+
+
+/// Errors that can happend while deserializing space settings.
+#[derive(Debug, Error)]
+pub enum DeError {
+    /// The bounds are missing or invalid.
+    #[error("the bounds are missing or invalid")]
+    MissingBounds,
+    /// The chunk size is invalid.
+    #[error("the chunk size is invalid")]
+    InvalidChunkSize,
+    /// Underlying Packed XML deserialization error.
+    #[error("pxml error: {0}")]
+    Pxml(#[from] pxml::DeError),
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn settings(min_x: Value, max_x: i64, min_y: i64, max_y: i64) -> Element {
+
+        let mut bounds = Element::new();
+        bounds.add_children("minX", min_x);
+        bounds.add_children("maxX", Value::Integer(max_x));
+        bounds.add_children("minY", Value::Integer(min_y));
+        bounds.add_children("maxY", Value::Integer(max_y));
+
+        let mut skyboxes = Element::new();
+        skyboxes.add_children("skyBox", Value::String("sky.model".to_string()));
+
+        let mut root = Element::new();
+        root.add_children("bounds", Value::Element(Box::new(bounds)));
+        root.add_children("skyBoxes", Value::Element(Box::new(skyboxes)));
+        root.add_children("timeOfDay", Value::String("tod.xml".to_string()));
+        root
+
+    }
+
+    #[test]
+    fn read_settings() {
+
+        // Integers may be stored as strings.
+        let space = from_element(&settings(Value::String(" -2 ".to_string()), 1, -3, 2)).unwrap();
+        assert_eq!((space.min_x, space.max_x, space.min_y, space.max_y), (-2, 1, -3, 2));
+        assert_eq!(space.chunk_size, DEFAULT_CHUNK_SIZE);
+        assert_eq!(space.time_of_day.as_deref(), Some("tod.xml"));
+        assert_eq!(space.skyboxes, ["sky.model"]);
+        assert_eq!(space.terrain_version, None);
+
+        assert_eq!(space.chunk_dimensions(), (4, 6));
+        assert_eq!(space.world_bounds(), (Vec3::new(-200.0, 0.0, -300.0), Vec3::new(200.0, 0.0, 300.0)));
+        assert!(space.contains_chunk(-2, 2));
+        assert!(!space.contains_chunk(2, 2));
+        assert_eq!(space.world_to_chunk(Vec3::new(-150.0, 0.0, 250.0)), (-2, 2));
+
+    }
+
+    #[test]
+    fn read_settings_large_bounds() {
+
+        let min = i32::MIN as i64;
+        let max = i32::MAX as i64;
+
+        let space = from_element(&settings(Value::Integer(min), max, min, max)).unwrap();
+        assert_eq!(space.chunk_dimensions(), (1 << 32, 1 << 32));
+        assert!(space.world_bounds().1.x > 0.0);
+
+        // Bounds that don't fit in 32 bits are rejected instead of truncated.
+        assert!(matches!(from_element(&settings(Value::Integer(min - 1), 0, 0, 0)), Err(DeError::MissingBounds)));
+        assert!(matches!(from_element(&settings(Value::Integer(0), max + 1, 0, 0)), Err(DeError::MissingBounds)));
+        assert!(matches!(from_element(&settings(Value::Integer(1), 0, 0, 0)), Err(DeError::MissingBounds)));
+
+    }
+
+}
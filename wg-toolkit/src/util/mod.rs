@@ -5,6 +5,8 @@ use std::fmt::Write;
 pub mod cursor;
 pub mod fnv;
 pub mod io;
+pub mod spatial;
+pub mod symbol;
 
 
 /// Make a string from an escaped sequence of bytes.
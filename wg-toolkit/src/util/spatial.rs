@@ -0,0 +1,222 @@
+//! Spatial index for querying entities around a position, this is the base
+//! structure for computing areas of interest.
+//!
+//! Positions are 3D but only the horizontal X/Z plane is indexed, like the
+//! game's spaces.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use glam::Vec3;
+
+
+/// A uniform grid spatial index mapping keys to their position.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid<K> {
+    /// Size of a grid cell on both axes.
+    cell_size: f32,
+    /// Keys in each non-empty cell.
+    cells: HashMap<(i32, i32), Vec<K>>,
+    /// Position of each key.
+    positions: HashMap<K, Vec3>,
+}
+
+/// Keys that entered and left an area between two queries.
+#[derive(Debug, Clone)]
+pub struct SpatialDelta<K> {
+    pub entered: Vec<K>,
+    pub left: Vec<K>,
+}
+
+impl<K> SpatialGrid<K>
+where
+    K: Copy + Eq + Hash
+{
+
+    /// Create a new grid with the given cell size, for best performance this
+    /// should be in the order of magnitude of the radius used in queries.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Get the position of the given key.
+    #[inline]
+    pub fn position(&self, key: K) -> Option<Vec3> {
+        self.positions.get(&key).copied()
+    }
+
+    /// Insert a key at the given position, or move it if already present.
+    pub fn insert(&mut self, key: K, pos: Vec3) {
+        let new_cell = self.cell_of(pos);
+        if let Some(old_pos) = self.positions.insert(key, pos) {
+            let old_cell = self.cell_of(old_pos);
+            if old_cell == new_cell {
+                return;
+            }
+            self.remove_from_cell(old_cell, key);
+        }
+        self.cells.entry(new_cell).or_default().push(key);
+    }
+
+    /// Remove a key, returning its last position if it was present.
+    pub fn remove(&mut self, key: K) -> Option<Vec3> {
+        let pos = self.positions.remove(&key)?;
+        self.remove_from_cell(self.cell_of(pos), key);
+        Some(pos)
+    }
+
+    /// Iterate over all keys within the given radius around a center, the
+    /// radius must be finite.
+    pub fn query_radius(&self, center: Vec3, radius: f32) -> impl Iterator<Item = K> + '_ {
+
+        assert!(radius.is_finite(), "radius must be finite");
+
+        let (min_x, min_z) = self.cell_of(center - Vec3::new(radius, 0.0, radius));
+        let (max_x, max_z) = self.cell_of(center + Vec3::new(radius, 0.0, radius));
+        let radius_squared = radius * radius;
+
+        // If the radius covers more cells than there are non-empty cells, it's
+        // faster to filter non-empty cells than to look up each covered cell.
+        let width = (max_x as i64 - min_x as i64 + 1).max(0) as u64;
+        let height = (max_z as i64 - min_z as i64 + 1).max(0) as u64;
+        let by_lookup = width.saturating_mul(height) <= self.cells.len() as u64;
+
+        let lookup_cells = by_lookup.then(|| {
+            (min_x..=max_x)
+                .flat_map(move |x| (min_z..=max_z).map(move |z| (x, z)))
+                .filter_map(|cell| self.cells.get(&cell))
+        });
+
+        let filter_cells = (!by_lookup).then(|| {
+            self.cells.iter()
+                .filter(move |&(&(x, z), _)| x >= min_x && x <= max_x && z >= min_z && z <= max_z)
+                .map(|(_, keys)| keys)
+        });
+
+        lookup_cells.into_iter().flatten()
+            .chain(filter_cells.into_iter().flatten())
+            .flatten()
+            .copied()
+            .filter(move |key| {
+                let pos = self.positions[key];
+                let dx = pos.x - center.x;
+                let dz = pos.z - center.z;
+                dx * dx + dz * dz <= radius_squared
+            })
+
+    }
+
+    /// Query all keys within the given radius around a center and compute
+    /// the difference with the previous set of keys, which is then updated
+    /// to the new set. This is typically called each tick for each client.
+    pub fn query_delta(&self, center: Vec3, radius: f32, set: &mut HashSet<K>) -> SpatialDelta<K> {
+
+        let current: HashSet<K> = self.query_radius(center, radius).collect();
+        let entered = current.difference(set).copied().collect();
+        let left = set.difference(&current).copied().collect();
+        *set = current;
+
+        SpatialDelta { entered, left }
+
+    }
+
+    /// Internal function to get the cell coordinates of a position.
+    #[inline]
+    fn cell_of(&self, pos: Vec3) -> (i32, i32) {
+        ((pos.x / self.cell_size).floor() as i32, (pos.z / self.cell_size).floor() as i32)
+    }
+
+    /// Internal function to remove a key from a cell, the cell is removed if
+    /// it becomes empty.
+    fn remove_from_cell(&mut self, cell: (i32, i32), key: K) {
+        if let Some(keys) = self.cells.get_mut(&cell) {
+            if let Some(index) = keys.iter().position(|&k| k == key) {
+                keys.swap_remove(index);
+            }
+            if keys.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn grid_query_delta() {
+
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(1u32, Vec3::new(0.0, 0.0, 0.0));
+        grid.insert(2, Vec3::new(5.0, 100.0, 5.0));
+        grid.insert(3, Vec3::new(-25.0, 0.0, 0.0));
+
+        let mut keys: Vec<u32> = grid.query_radius(Vec3::ZERO, 10.0).collect();
+        keys.sort();
+        assert_eq!(keys, [1, 2]);
+
+        let mut set = HashSet::new();
+        let delta = grid.query_delta(Vec3::ZERO, 10.0, &mut set);
+        assert_eq!(delta.entered.len(), 2);
+        assert!(delta.left.is_empty());
+
+        grid.insert(2, Vec3::new(50.0, 0.0, 0.0));
+        grid.insert(3, Vec3::new(-9.0, 0.0, 0.0));
+        let delta = grid.query_delta(Vec3::ZERO, 10.0, &mut set);
+        assert_eq!(delta.entered, [3]);
+        assert_eq!(delta.left, [2]);
+
+        assert_eq!(grid.remove(1), Some(Vec3::ZERO));
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.query_radius(Vec3::ZERO, 10.0).collect::<Vec<_>>(), [3]);
+
+    }
+
+    #[test]
+    fn grid_query_large_radius() {
+
+        let mut grid = SpatialGrid::new(1.0);
+        grid.insert(1u32, Vec3::new(0.0, 0.0, 0.0));
+        grid.insert(2, Vec3::new(1e6, 0.0, -1e6));
+        grid.insert(3, Vec3::new(-3e6, 0.0, 0.0));
+
+        // Non-empty cells are filtered instead of looking up all covered cells.
+        let mut keys: Vec<u32> = grid.query_radius(Vec3::ZERO, 2e6).collect();
+        keys.sort();
+        assert_eq!(keys, [1, 2]);
+
+        let mut keys: Vec<u32> = grid.query_radius(Vec3::ZERO, f32::MAX).collect();
+        keys.sort();
+        assert_eq!(keys, [1, 2, 3]);
+
+        assert_eq!(grid.query_radius(Vec3::ZERO, -1.0).count(), 0);
+
+    }
+
+    #[test]
+    #[should_panic(expected = "radius must be finite")]
+    fn grid_query_infinite_radius() {
+        let grid = SpatialGrid::<u32>::new(1.0);
+        let _ = grid.query_radius(Vec3::ZERO, f32::INFINITY);
+    }
+
+}
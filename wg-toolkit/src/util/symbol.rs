@@ -0,0 +1,91 @@
+//! Interned strings, used for names that are compared very often such as
+//! entity methods and properties.
+
+use std::collections::HashMap;
+use std::fmt;
+
+
+/// An interned string, only valid for the [`SymbolTable`] that produced it.
+/// Comparing two symbols is a simple integer comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+
+    /// Return the raw index of this symbol in its table.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+}
+
+/// A table of interned strings, each unique string is stored once and is
+/// given a [`Symbol`].
+#[derive(Default, Clone)]
+pub struct SymbolTable {
+    /// Mapping of strings to their symbol.
+    symbols: HashMap<Box<str>, Symbol>,
+    /// Strings of each symbol, indexed by symbol.
+    names: Vec<Box<str>>,
+}
+
+impl SymbolTable {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intern the given string, returning the existing symbol if the string
+    /// is already interned.
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name.into());
+        self.symbols.insert(name.into(), symbol);
+        symbol
+    }
+
+    /// Get the symbol of a string if it's already interned.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    /// Get the string of a symbol, panics if the symbol doesn't come from
+    /// this table.
+    #[inline]
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.index()]
+    }
+
+    /// Get the string of a symbol, if the symbol is valid in this table.
+    #[inline]
+    pub fn try_resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.names.get(symbol.index()).map(|name| &**name)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Iterate over all symbols and their string, in interning order.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> + '_ {
+        self.names.iter().enumerate().map(|(i, name)| (Symbol(i as u32), &**name))
+    }
+
+}
+
+impl fmt::Debug for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names.iter()).finish()
+    }
+}